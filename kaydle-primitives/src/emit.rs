@@ -0,0 +1,285 @@
+/*!
+Writers for the individual components of a KDL document. These are the
+inverse of the parsers in the rest of `kaydle-primitives`: everything they
+write can be parsed back into the same value. Identifiers are written bare
+when possible, and fall back to quoted strings otherwise.
+
+All of the writers write to a [`fmt::Write`], so they can be used to write to
+a `String` or directly to a [`Formatter`][fmt::Formatter].
+
+```
+use kaydle_primitives::emit::{write_annotation, write_identifier, write_string};
+
+let mut output = String::new();
+write_annotation(&mut output, "type").unwrap();
+write_identifier(&mut output, "node").unwrap();
+output.push(' ');
+write_identifier(&mut output, "two words").unwrap();
+output.push(' ');
+write_string(&mut output, "line\nbreak").unwrap();
+
+assert_eq!(output, r#"(type)node "two words" "line\nbreak""#);
+```
+*/
+
+use std::fmt::{self, Write};
+
+use crate::{
+    number::KdlNumber,
+    string::{is_identifier, is_initial_identifier},
+    value::KdlValue,
+    whitespace::{is_newline, is_plain_whitespace},
+};
+
+/// Returns true if this string can be written as a bare identifier. Strings
+/// that are empty, contain non-identifier characters, look like numbers, or
+/// are keywords (`true`, `false`, `null`) must be quoted.
+pub fn is_bare_identifier(identifier: &str) -> bool {
+    let mut chars = identifier.chars();
+
+    let first = match chars.next() {
+        Some(c) if is_initial_identifier(c) => c,
+        _ => return false,
+    };
+
+    // A sign or a dot followed by a digit would be parsed as a number
+    let looks_like_number = matches!(first, '-' | '+' | '.')
+        && matches!(chars.clone().next(), Some(c) if c.is_ascii_digit());
+
+    !looks_like_number
+        && !matches!(identifier, "true" | "false" | "null")
+        && identifier
+            .chars()
+            .all(|c| is_identifier(c) && !is_plain_whitespace(c) && !is_newline(c))
+}
+
+/// Write an identifier, such as a node name or property key. It's written
+/// bare if possible, or as an escaped string otherwise.
+pub fn write_identifier(out: &mut impl Write, identifier: &str) -> fmt::Result {
+    if is_bare_identifier(identifier) {
+        out.write_str(identifier)
+    } else {
+        write_string(out, identifier)
+    }
+}
+
+/// Write a quoted string, escaping characters as necessary.
+pub fn write_string(out: &mut impl Write, value: &str) -> fmt::Result {
+    out.write_char('"')?;
+
+    let mut tail = value;
+
+    while let Some(index) = tail.find(|c: char| escape(c).is_some() || c.is_control()) {
+        let (chunk, rest) = tail.split_at(index);
+        out.write_str(chunk)?;
+
+        let mut chars = rest.chars();
+        let c = chars.next().expect("found a character at this index");
+
+        match escape(c) {
+            Some(escape) => out.write_str(escape)?,
+            None => write!(out, "\\u{{{:x}}}", c as u32)?,
+        }
+
+        tail = chars.as_str();
+    }
+
+    out.write_str(tail)?;
+    out.write_char('"')
+}
+
+/// Get the named escape sequence for a character, if it has one.
+fn escape(c: char) -> Option<&'static str> {
+    Some(match c {
+        '"' => "\\\"",
+        '\\' => "\\\\",
+        '\n' => "\\n",
+        '\r' => "\\r",
+        '\t' => "\\t",
+        '\u{08}' => "\\b",
+        '\u{0C}' => "\\f",
+        _ => return None,
+    })
+}
+
+/// Write a raw string, like `r#"abc"#`, with the minimum number of `#`
+/// needed to contain the string.
+pub fn write_raw_string(out: &mut impl Write, value: &str) -> fmt::Result {
+    // The string ends at the first `"` followed by the right number of `#`,
+    // so we need one more `#` than the longest such run in the string.
+    let hashes = value
+        .match_indices('"')
+        .map(|(index, _)| {
+            value[index + 1..]
+                .bytes()
+                .take_while(|&b| b == b'#')
+                .count()
+                + 1
+        })
+        .max()
+        .unwrap_or(0);
+
+    out.write_char('r')?;
+    (0..hashes).try_for_each(|_| out.write_char('#'))?;
+    out.write_char('"')?;
+    out.write_str(value)?;
+    out.write_char('"')?;
+    (0..hashes).try_for_each(|_| out.write_char('#'))
+}
+
+/// Write a number. Floats are always written with a fractional component or
+/// exponent, so that they can be distinguished from integers.
+///
+/// # Errors
+///
+/// KDL has no representation for infinite or NaN floats, so those return an
+/// error.
+pub fn write_number(out: &mut impl Write, value: KdlNumber) -> fmt::Result {
+    match value {
+        KdlNumber::Signed(value) => write!(out, "{}", value),
+        KdlNumber::Unsigned(value) => write!(out, "{}", value),
+        KdlNumber::Float(value) if !value.is_finite() => Err(fmt::Error),
+        // The debug format always includes a `.` or an exponent
+        KdlNumber::Float(value) => write!(out, "{:?}", value),
+    }
+}
+
+/// Write any KDL value. Strings are written as escaped strings.
+///
+/// # Errors
+///
+/// See [`write_number`].
+pub fn write_value(out: &mut impl Write, value: &KdlValue<'_>) -> fmt::Result {
+    match value {
+        KdlValue::Null => out.write_str("null"),
+        KdlValue::Bool(true) => out.write_str("true"),
+        KdlValue::Bool(false) => out.write_str("false"),
+        KdlValue::Number(number) => write_number(out, *number),
+        KdlValue::String(string) => write_string(out, string.as_str()),
+    }
+}
+
+/// Write a type annotation, like `(type)`.
+pub fn write_annotation(out: &mut impl Write, annotation: &str) -> fmt::Result {
+    out.write_char('(')?;
+    write_identifier(out, annotation)?;
+    out.write_char(')')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        number::parse_number,
+        string::{parse_identifier, parse_string, KdlString},
+    };
+
+    fn emit(write: impl FnOnce(&mut String) -> fmt::Result) -> String {
+        let mut output = String::new();
+        write(&mut output).expect("failed to write");
+        output
+    }
+
+    /// Write an identifier and ensure it parses back to the same string
+    fn identifier(identifier: &str) -> String {
+        let output = emit(|out| write_identifier(out, identifier));
+        let parsed: Result<(&str, KdlString<'_>), nom::Err<()>> = parse_identifier(&output);
+        let (tail, parsed) = parsed.expect("failed to parse written identifier");
+
+        assert_eq!(tail, "");
+        assert_eq!(parsed, identifier);
+        output
+    }
+
+    /// Write a string and ensure it parses back to the same string
+    fn string(value: &str, write: fn(&mut String, &str) -> fmt::Result) -> String {
+        let output = emit(|out| write(out, value));
+        let parsed: Result<(&str, KdlString<'_>), nom::Err<()>> = parse_string(&output);
+        let (tail, parsed) = parsed.expect("failed to parse written string");
+
+        assert_eq!(tail, "");
+        assert_eq!(parsed, value);
+        output
+    }
+
+    #[test]
+    fn bare_identifiers() {
+        assert_eq!(identifier("node"), "node");
+        assert_eq!(identifier("kebab-case"), "kebab-case");
+        assert_eq!(identifier("-"), "-");
+        assert_eq!(identifier("über"), "über");
+    }
+
+    #[test]
+    fn quoted_identifiers() {
+        assert_eq!(identifier(""), r#""""#);
+        assert_eq!(identifier("two words"), r#""two words""#);
+        assert_eq!(identifier("1st"), r#""1st""#);
+        assert_eq!(identifier("-1"), r#""-1""#);
+        assert_eq!(identifier("true"), r#""true""#);
+        assert_eq!(identifier("null"), r#""null""#);
+        assert_eq!(identifier("a=b"), r#""a=b""#);
+        assert_eq!(identifier("(x)"), r#""(x)""#);
+    }
+
+    #[test]
+    fn escaped_strings() {
+        assert_eq!(string("plain", write_string), r#""plain""#);
+        assert_eq!(
+            string("quote \" backslash \\ tab \t", write_string),
+            r#""quote \" backslash \\ tab \t""#
+        );
+        assert_eq!(string("bell \u{07}", write_string), r#""bell \u{7}""#);
+        assert_eq!(string("slash /", write_string), r#""slash /""#);
+    }
+
+    #[test]
+    fn raw_strings() {
+        assert_eq!(string("plain", write_raw_string), r#"r"plain""#);
+        assert_eq!(string(r"C:\path", write_raw_string), r#"r"C:\path""#);
+        assert_eq!(
+            string(r#"a "quote""#, write_raw_string),
+            r##"r#"a "quote""#"##
+        );
+        assert_eq!(
+            string(r##"tricky "# and ""##, write_raw_string),
+            r###"r##"tricky "# and ""##"###
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        for number in [
+            KdlNumber::Unsigned(10),
+            KdlNumber::Signed(-10),
+            KdlNumber::Float(1.5),
+            KdlNumber::Float(-2.0),
+            KdlNumber::Float(1e100),
+            KdlNumber::Float(1.5e-7),
+        ] {
+            let output = emit(|out| write_number(out, number));
+            let parsed: Result<(&str, KdlNumber), nom::Err<()>> = parse_number(&output);
+            let (tail, parsed) = parsed.expect("failed to parse written number");
+
+            assert_eq!(tail, "");
+            assert_eq!(parsed, number, "{}", output);
+        }
+
+        assert_eq!(emit(|out| write_number(out, KdlNumber::Float(3.0))), "3.0");
+        assert!(write_number(&mut String::new(), KdlNumber::Float(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn values_and_annotations() {
+        assert_eq!(
+            emit(|out| {
+                write_annotation(out, "date time")?;
+                write_value(out, &KdlValue::String(KdlString::from_borrowed("today")))?;
+                out.push(' ');
+                write_annotation(out, "u8")?;
+                write_value(out, &KdlValue::Null)
+            }),
+            r#"("date time")"today" (u8)null"#
+        );
+    }
+}
@@ -0,0 +1,598 @@
+/*!
+A structured error type for the parsers in this crate.
+
+The parsers are generic over their nom error type, so callers that only care
+whether parsing succeeded can use `()`. [`ParseError`] is for callers that
+want to tell a user *what* went wrong, and where.
+
+nom parsers only ever see the remaining input, not the input they started
+with, so an error produced by a parser doesn't know its own position in the
+document. Instead, its `offset` is initially the length of the input that
+was remaining when the error occurred. [`ParseError::locate`] converts this
+into a byte offset from the start of the original input:
+
+```
+use kaydle_primitives::{error::ParseError, events::Events};
+
+let input = "node 1\nnode \"abc";
+let err = Events::<ParseError>::new(input)
+    .find_map(Result::err)
+    .expect("the string is unterminated");
+
+let err = match err {
+    nom::Err::Error(err) | nom::Err::Failure(err) => err.locate(input),
+    nom::Err::Incomplete(_) => unreachable!(),
+};
+
+assert_eq!(err, ParseError::UnterminatedString { offset: 12 });
+```
+*/
+
+use std::{char::CharTryFromError, error::Error, fmt};
+
+use nom::error::{ErrorKind, FromExternalError};
+use nom_supreme::{context::ContextError, tag::TagError};
+
+use crate::{
+    number::BoundsError,
+    string::{is_identifier, is_initial_identifier},
+};
+
+/// A KDL syntax error. See the [module][self] docs for an explanation of
+/// `offset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// A `"` string was never closed
+    UnterminatedString {
+        /// The byte offset of the opening `"`
+        offset: usize,
+    },
+
+    /// An `r"` or `r#"` raw string was never closed
+    UnterminatedRawString {
+        /// The byte offset of the opening `r`
+        offset: usize,
+    },
+
+    /// A `\` in a string wasn't followed by a valid escape
+    InvalidEscape {
+        /// The byte offset of the character after the `\`
+        offset: usize,
+    },
+
+    /// A `\u{...}` escape was malformed, or wasn't a valid unicode scalar
+    /// value
+    InvalidUnicodeEscape {
+        /// The byte offset of the escape, after the `\`
+        offset: usize,
+    },
+
+    /// A number literal was too large (or too small) to be represented
+    NumberOutOfRange {
+        /// The byte offset of the number
+        offset: usize,
+
+        /// The type that the number couldn't fit into, like `"u64"`
+        target: &'static str,
+
+        /// The number, as written in the document
+        literal: String,
+    },
+
+    /// A character appeared that isn't allowed at that point in the document
+    UnexpectedCharacter {
+        /// The byte offset of the character
+        offset: usize,
+
+        /// The unexpected character
+        character: char,
+    },
+
+    /// The input ended in the middle of something
+    UnexpectedEnd {
+        /// The byte offset of the end of the input
+        offset: usize,
+    },
+
+    /// A `{` children block was never closed with a `}`
+    UnclosedChildren {
+        /// The byte offset of the end of the input
+        offset: usize,
+    },
+
+    /// A `}` appeared without a matching `{`
+    UnexpectedCloseBrace {
+        /// The byte offset of the `}`
+        offset: usize,
+    },
+
+    /// A bare identifier started with a character that identifiers can't
+    /// start with, such as a digit
+    BadIdentifier {
+        /// The byte offset of the identifier
+        offset: usize,
+    },
+}
+
+impl ParseError {
+    /// The byte offset of this error. See the [module][self] docs for what
+    /// it means before [`locate`][Self::locate] is called.
+    pub fn offset(&self) -> usize {
+        match *self {
+            ParseError::UnterminatedString { offset }
+            | ParseError::UnterminatedRawString { offset }
+            | ParseError::InvalidEscape { offset }
+            | ParseError::InvalidUnicodeEscape { offset }
+            | ParseError::NumberOutOfRange { offset, .. }
+            | ParseError::UnexpectedCharacter { offset, .. }
+            | ParseError::UnexpectedEnd { offset }
+            | ParseError::UnclosedChildren { offset }
+            | ParseError::UnexpectedCloseBrace { offset }
+            | ParseError::BadIdentifier { offset } => offset,
+        }
+    }
+
+    fn offset_mut(&mut self) -> &mut usize {
+        match self {
+            ParseError::UnterminatedString { offset }
+            | ParseError::UnterminatedRawString { offset }
+            | ParseError::InvalidEscape { offset }
+            | ParseError::InvalidUnicodeEscape { offset }
+            | ParseError::NumberOutOfRange { offset, .. }
+            | ParseError::UnexpectedCharacter { offset, .. }
+            | ParseError::UnexpectedEnd { offset }
+            | ParseError::UnclosedChildren { offset }
+            | ParseError::UnexpectedCloseBrace { offset }
+            | ParseError::BadIdentifier { offset } => offset,
+        }
+    }
+
+    /// Convert the offset of an error returned by a parser into a byte
+    /// offset from the start of `input`, which must be the input originally
+    /// given to the parser. Only call this once per error.
+    #[must_use]
+    pub fn locate(mut self, input: &str) -> Self {
+        let offset = self.offset_mut();
+        *offset = input.len().saturating_sub(*offset);
+        self
+    }
+
+    /// A description of this error, without its offset.
+    pub fn message(&self) -> String {
+        match self {
+            ParseError::UnterminatedString { .. } => "unterminated string".to_owned(),
+            ParseError::UnterminatedRawString { .. } => "unterminated raw string".to_owned(),
+            ParseError::InvalidEscape { .. } => "invalid escape sequence in string".to_owned(),
+            ParseError::InvalidUnicodeEscape { .. } => {
+                "invalid unicode escape sequence in string".to_owned()
+            }
+            ParseError::NumberOutOfRange {
+                target, literal, ..
+            } => format!("number {literal} is out of range for {target}"),
+            ParseError::UnexpectedCharacter { character, .. } => {
+                format!("unexpected character {character:?}")
+            }
+            ParseError::UnexpectedEnd { .. } => "unexpected end of input".to_owned(),
+            ParseError::UnclosedChildren { .. } => {
+                "unexpected end of input; children block was never closed".to_owned()
+            }
+            ParseError::UnexpectedCloseBrace { .. } => "unexpected `}`".to_owned(),
+            ParseError::BadIdentifier { .. } => "identifiers can't start with a digit".to_owned(),
+        }
+    }
+
+    /// A hint for how to fix this error, if there's a useful one.
+    pub fn help(&self) -> Option<&'static str> {
+        match self {
+            ParseError::UnterminatedString { .. } => Some("add a closing `\"`"),
+            ParseError::UnterminatedRawString { .. } => Some(
+                "raw strings end with a `\"` followed by as many `#` as they started with",
+            ),
+            ParseError::InvalidEscape { .. } => Some(
+                "valid escapes are \\n, \\r, \\t, \\\\, \\/, \\\", \\b, \\f, and \\u{...}",
+            ),
+            ParseError::InvalidUnicodeEscape { .. } => Some(
+                "unicode escapes have 1 to 6 hex digits, like `\\u{1F600}`, and can't be surrogates",
+            ),
+            ParseError::NumberOutOfRange { .. } => {
+                Some("if this is meant to be text rather than a number, quote it")
+            }
+            ParseError::UnclosedChildren { .. } => Some("add a `}` to close the children block"),
+            ParseError::UnexpectedCloseBrace { .. } => {
+                Some("remove it, or add the matching `{`")
+            }
+            ParseError::BadIdentifier { .. } => {
+                Some("identifiers that start with a digit must be quoted, like `\"1st\"`")
+            }
+            ParseError::UnexpectedCharacter { .. } | ParseError::UnexpectedEnd { .. } => None,
+        }
+    }
+
+    /// The length, in bytes, of the part of the input this error refers to,
+    /// starting at its offset. This is 0 for errors at the end of the input.
+    pub fn span_len(&self) -> usize {
+        match self {
+            ParseError::NumberOutOfRange { literal, .. } => literal.len(),
+            ParseError::UnexpectedCharacter { character, .. } => character.len_utf8(),
+            ParseError::UnexpectedCloseBrace { .. } => 1,
+            ParseError::UnexpectedEnd { .. } | ParseError::UnclosedChildren { .. } => 0,
+            ParseError::UnterminatedString { .. }
+            | ParseError::UnterminatedRawString { .. }
+            | ParseError::InvalidEscape { .. }
+            | ParseError::InvalidUnicodeEscape { .. }
+            | ParseError::BadIdentifier { .. } => 1,
+        }
+    }
+
+    /// True if, when combining errors from alternative parsers, `other`
+    /// should be preferred over `self`.
+    fn prefer(&self, other: &Self) -> bool {
+        match (self.is_generic(), other.is_generic()) {
+            (true, false) => true,
+            (false, true) => false,
+
+            // Prefer whichever error got further into the input. Offsets
+            // are measured from the end, so smaller is further.
+            _ => other.offset() < self.offset(),
+        }
+    }
+
+    /// True for the catch-all errors that don't say anything about the
+    /// construct being parsed. These are refined by context, or replaced by
+    /// more specific errors from alternative branches.
+    fn is_generic(&self) -> bool {
+        matches!(
+            self,
+            ParseError::UnexpectedCharacter { .. } | ParseError::UnexpectedEnd { .. }
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte offset {}", self.message(), self.offset())
+    }
+}
+
+impl Error for ParseError {}
+
+impl<'i> nom::error::ParseError<&'i str> for ParseError {
+    fn from_error_kind(input: &'i str, kind: ErrorKind) -> Self {
+        let offset = input.len();
+
+        match input.chars().next() {
+            None => ParseError::UnexpectedEnd { offset },
+            Some('}') => ParseError::UnexpectedCloseBrace { offset },
+
+            // parse_bare_identifier reports a non-initial identifier
+            // character as an Alpha error
+            Some(c)
+                if kind == ErrorKind::Alpha && is_identifier(c) && !is_initial_identifier(c) =>
+            {
+                ParseError::BadIdentifier { offset }
+            }
+            Some(character) => ParseError::UnexpectedCharacter { offset, character },
+        }
+    }
+
+    fn append(_input: &'i str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        if self.prefer(&other) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl<'i> TagError<&'i str, &'static str> for ParseError {
+    fn from_tag(input: &'i str, _tag: &'static str) -> Self {
+        nom::error::ParseError::from_error_kind(input, ErrorKind::Tag)
+    }
+}
+
+impl<'i> ContextError<&'i str, &'static str> for ParseError {
+    fn add_context(location: &'i str, ctx: &'static str, other: Self) -> Self {
+        let offset = location.len();
+
+        // Whether the error happened after the start of the context, as
+        // opposed to the context not matching at all.
+        let progressed = other.offset() < offset;
+
+        match (ctx, other) {
+            ("escaped string", ParseError::UnexpectedEnd { .. }) if progressed => {
+                ParseError::UnterminatedString { offset }
+            }
+            ("raw string", ParseError::UnexpectedEnd { .. }) if progressed => {
+                ParseError::UnterminatedRawString { offset }
+            }
+            ("escape", other) if other.is_generic() => ParseError::InvalidEscape { offset },
+            ("unicode escape", other)
+                if (other.is_generic() && progressed)
+                    || matches!(other, ParseError::InvalidUnicodeEscape { .. }) =>
+            {
+                ParseError::InvalidUnicodeEscape { offset }
+            }
+            ("end of children", ParseError::UnexpectedEnd { offset }) => {
+                ParseError::UnclosedChildren { offset }
+            }
+
+            // Out of range errors are reported partway through the number,
+            // so widen them to cover the whole literal.
+            ("number", ParseError::NumberOutOfRange { target, .. }) => {
+                ParseError::NumberOutOfRange {
+                    offset,
+                    target,
+                    literal: number_literal(location),
+                }
+            }
+            (_, other) => other,
+        }
+    }
+}
+
+impl<'i> FromExternalError<&'i str, CharTryFromError> for ParseError {
+    fn from_external_error(input: &'i str, _kind: ErrorKind, _e: CharTryFromError) -> Self {
+        ParseError::InvalidUnicodeEscape {
+            offset: input.len(),
+        }
+    }
+}
+
+impl<'i> FromExternalError<&'i str, BoundsError> for ParseError {
+    fn from_external_error(input: &'i str, _kind: ErrorKind, e: BoundsError) -> Self {
+        ParseError::NumberOutOfRange {
+            offset: input.len(),
+            target: e.target,
+            literal: number_literal(input),
+        }
+    }
+}
+
+/// A [`ParseError`], along with the nom-supreme contexts that were being
+/// parsed when it happened, like `["node", "property", "value"]`. This is
+/// useful for explaining an error to a human; use a bare [`ParseError`] if
+/// you don't need it, since collecting contexts allocates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextualError {
+    /// The underlying error
+    pub error: ParseError,
+
+    /// The contexts that were being parsed when the error happened, from
+    /// outermost to innermost.
+    pub context: Vec<&'static str>,
+}
+
+impl ContextualError {
+    /// Convert the offset of the underlying error; see
+    /// [`ParseError::locate`].
+    #[must_use]
+    pub fn locate(self, input: &str) -> Self {
+        Self {
+            error: self.error.locate(input),
+            ..self
+        }
+    }
+}
+
+impl From<ParseError> for ContextualError {
+    fn from(error: ParseError) -> Self {
+        Self {
+            error,
+            context: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for ContextualError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+
+        if let Some((first, rest)) = self.context.split_first() {
+            write!(f, ", while parsing {first}")?;
+            rest.iter().try_for_each(|ctx| write!(f, " → {ctx}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ContextualError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<'i> nom::error::ParseError<&'i str> for ContextualError {
+    fn from_error_kind(input: &'i str, kind: ErrorKind) -> Self {
+        ParseError::from_error_kind(input, kind).into()
+    }
+
+    fn append(_input: &'i str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        if self.error.prefer(&other.error) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl<'i> TagError<&'i str, &'static str> for ContextualError {
+    fn from_tag(input: &'i str, tag: &'static str) -> Self {
+        ParseError::from_tag(input, tag).into()
+    }
+}
+
+impl<'i> ContextError<&'i str, &'static str> for ContextualError {
+    fn add_context(location: &'i str, ctx: &'static str, mut other: Self) -> Self {
+        other.error = ParseError::add_context(location, ctx, other.error);
+
+        // Some parsers add the same context as the parser wrapping them,
+        // like "string"
+        if other.context.first() != Some(&ctx) {
+            other.context.insert(0, ctx);
+        }
+
+        other
+    }
+}
+
+impl<'i, X> FromExternalError<&'i str, X> for ContextualError
+where
+    ParseError: FromExternalError<&'i str, X>,
+{
+    fn from_external_error(input: &'i str, kind: ErrorKind, e: X) -> Self {
+        ParseError::from_external_error(input, kind, e).into()
+    }
+}
+
+/// Get the number literal at the start of `input`. Every character that can
+/// appear in a number is also an identifier character.
+fn number_literal(input: &str) -> String {
+    let end = input
+        .find(|c: char| !is_identifier(c))
+        .unwrap_or(input.len());
+
+    input[..end].to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Events;
+
+    /// Parse a whole document, and return its first error
+    fn first_error(input: &str) -> ParseError {
+        match Events::<ParseError>::new(input).find_map(Result::err) {
+            None => panic!("expected an error from {input:?}"),
+            Some(nom::Err::Error(err) | nom::Err::Failure(err)) => err.locate(input),
+            Some(nom::Err::Incomplete(_)) => panic!("unexpected Incomplete"),
+        }
+    }
+
+    #[test]
+    fn unterminated_string() {
+        assert_eq!(
+            first_error("a 1\nb \"hello"),
+            ParseError::UnterminatedString { offset: 6 }
+        );
+    }
+
+    #[test]
+    fn unterminated_raw_string() {
+        assert_eq!(
+            first_error("a r#\"hello\"\n"),
+            ParseError::UnterminatedRawString { offset: 2 }
+        );
+    }
+
+    #[test]
+    fn invalid_escape() {
+        assert_eq!(
+            first_error(r#"a "ab\qc""#),
+            ParseError::InvalidEscape { offset: 6 }
+        );
+    }
+
+    #[test]
+    fn invalid_unicode_escape() {
+        assert_eq!(
+            first_error(r#"a "\u{D800}""#),
+            ParseError::InvalidUnicodeEscape { offset: 4 }
+        );
+        assert_eq!(
+            first_error(r#"a "\u{zz}""#),
+            ParseError::InvalidUnicodeEscape { offset: 4 }
+        );
+    }
+
+    #[test]
+    fn number_out_of_range() {
+        assert_eq!(
+            first_error("a 1 99999999999999999999"),
+            ParseError::NumberOutOfRange {
+                offset: 4,
+                target: "u64",
+                literal: "99999999999999999999".to_owned()
+            }
+        );
+        assert_eq!(
+            first_error("a -0xFFFF_FFFF_FFFF_FFFF"),
+            ParseError::NumberOutOfRange {
+                offset: 2,
+                target: "i64",
+                literal: "-0xFFFF_FFFF_FFFF_FFFF".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn unexpected_character() {
+        assert_eq!(
+            first_error("a 1 =\n"),
+            ParseError::UnexpectedCharacter {
+                offset: 4,
+                character: '='
+            }
+        );
+    }
+
+    #[test]
+    fn unclosed_children() {
+        assert_eq!(
+            first_error("a {\n    b\n"),
+            ParseError::UnclosedChildren { offset: 10 }
+        );
+    }
+
+    #[test]
+    fn unexpected_close_brace() {
+        assert_eq!(
+            first_error("a\n}\n"),
+            ParseError::UnexpectedCloseBrace { offset: 2 }
+        );
+    }
+
+    #[test]
+    fn bad_identifier() {
+        assert_eq!(
+            first_error("a\n1abc\n"),
+            ParseError::BadIdentifier { offset: 2 }
+        );
+    }
+
+    #[test]
+    fn context() {
+        let input = "a 1 key=\"abc";
+        let err = match Events::<ContextualError>::new(input).find_map(Result::err) {
+            Some(nom::Err::Failure(err)) => err.locate(input),
+            err => panic!("expected a failure, got {err:?}"),
+        };
+
+        assert_eq!(err.error, ParseError::UnterminatedString { offset: 8 });
+        assert_eq!(
+            err.context,
+            ["property", "value", "string", "escaped string"]
+        );
+        assert_eq!(
+            err.to_string(),
+            "unterminated string at byte offset 8, while parsing property → value → string → escaped string"
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            first_error("a\n}\n").to_string(),
+            "unexpected `}` at byte offset 2"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+/*!
+A flat, iterator-style pull parser for KDL documents.
+
+The [processors][crate::node] in the `node` module are move-oriented: each step
+consumes the processor, and every node must be fully drained before moving on
+to the next one. That's a good fit for a serde deserializer, but it can be
+awkward for hand-written loaders. [`Events`] instead yields a flat stream of
+[`Event`]s for the entire document, and keeps track of the nesting depth
+itself, so a loader can be written as a simple loop:
+
+```
+use kaydle_primitives::events::{Event, Events};
+
+let mut events = Events::<()>::new("server port=8080 { tls; }");
+let mut names = Vec::new();
+
+while let Some(event) = events.next_event().expect("parse error") {
+    if let Event::StartNode { name, .. } = event {
+        names.push((events.depth(), name.into_string()));
+    }
+}
+
+assert_eq!(names, [(0, "server".to_owned()), (1, "tls".to_owned())]);
+```
+
+Every [`StartNode`][Event::StartNode] is matched by an
+[`EndNode`][Event::EndNode], and every
+[`StartChildren`][Event::StartChildren] is matched by an
+[`EndChildren`][Event::EndChildren], which is always immediately followed by
+the `EndNode` of the node that owns the children.
+*/
+
+use std::{char::CharTryFromError, marker::PhantomData};
+
+use nom::{
+    error::{FromExternalError, ParseError},
+    Err as NomErr,
+};
+use nom_supreme::{context::ContextError, tag::TagError};
+
+use crate::{
+    annotation::AnnotatedValue,
+    node::{parse_node_event, parse_node_start, run_parser_on, InternalNodeEvent, ListEnd},
+    number::BoundsError,
+    property::Property,
+    string::KdlString,
+};
+
+/// A single event from a KDL document, as returned by [`Events`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'i> {
+    /// The start of a node
+    StartNode {
+        /// The name of the node
+        name: KdlString<'i>,
+
+        /// The type annotation of the node, if any
+        annotation: Option<KdlString<'i>>,
+    },
+
+    /// An argument of the current node
+    Argument(AnnotatedValue<'i>),
+
+    /// A property of the current node
+    Property(Property<'i>),
+
+    /// The start of the children block (`{`) of the current node. Subsequent
+    /// nodes, until the matching [`EndChildren`][Event::EndChildren], are
+    /// children of this node.
+    StartChildren,
+
+    /// The end of the children block (`}`) of the current node
+    EndChildren,
+
+    /// The end of the current node
+    EndNode,
+}
+
+/// Where the pull parser is in the document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Between nodes; the next thing is either a node or the end of the
+    /// current node list
+    Nodes,
+
+    /// In the middle of a node; the next thing is an argument, a property,
+    /// children, or the end of the node
+    Content,
+
+    /// The children of a node just ended, which also ends the node
+    ChildrenEnded,
+
+    /// The document is finished, or there was an error. Nothing else will
+    /// be returned.
+    Done,
+}
+
+/// A pull parser that returns the contents of a KDL document as a flat
+/// sequence of [`Event`]s. See the [module docs][self] for details.
+///
+/// `Events` is fused: after it returns the end of the document or an error, it
+/// returns `None` forever.
+#[derive(Debug, Clone)]
+pub struct Events<'i, E = ()> {
+    /// The currently unparsed input string, as a suffix of the original input.
+    state: &'i str,
+    position: State,
+    depth: usize,
+    error: PhantomData<E>,
+}
+
+impl<'i, E> Events<'i, E> {
+    /// Create a new pull parser over a KDL document.
+    pub fn new(input: &'i str) -> Self {
+        Self {
+            state: input,
+            position: State::Nodes,
+            depth: 0,
+            error: PhantomData,
+        }
+    }
+
+    /// The number of children blocks containing the most recently returned
+    /// event. Top-level nodes have depth 0; the [`StartChildren`] and
+    /// [`EndChildren`] of a top-level node, as well as its children, have
+    /// depth 1.
+    ///
+    /// [`StartChildren`]: Event::StartChildren
+    /// [`EndChildren`]: Event::EndChildren
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The part of the input that hasn't been parsed yet.
+    pub fn remaining(&self) -> &'i str {
+        self.state
+    }
+}
+
+impl<'i, E> Events<'i, E>
+where
+    E: ParseError<&'i str>,
+    E: TagError<&'i str, &'static str>,
+    E: FromExternalError<&'i str, CharTryFromError>,
+    E: FromExternalError<&'i str, BoundsError>,
+    E: ContextError<&'i str, &'static str>,
+{
+    /// Get the next event from the document, or `None` at the end of the
+    /// document.
+    pub fn next_event(&mut self) -> Result<Option<Event<'i>>, NomErr<E>> {
+        let result = self.parse_event();
+
+        if !matches!(result, Ok(Some(_))) {
+            self.position = State::Done;
+        }
+
+        result
+    }
+
+    fn parse_event(&mut self) -> Result<Option<Event<'i>>, NomErr<E>> {
+        match self.position {
+            State::Done => Ok(None),
+            State::ChildrenEnded => {
+                // The depth of the EndChildren event is the depth inside the
+                // children block, so it's only decremented now.
+                self.depth -= 1;
+                self.position = State::Nodes;
+                Ok(Some(Event::EndNode))
+            }
+            State::Nodes => {
+                let end = match self.depth {
+                    0 => ListEnd::Eof,
+                    _ => ListEnd::Brace,
+                };
+
+                match run_parser_on(&mut self.state, parse_node_start(end.parser()))? {
+                    Some(node) => {
+                        self.position = State::Content;
+                        Ok(Some(Event::StartNode {
+                            name: node.item,
+                            annotation: node.annotation,
+                        }))
+                    }
+                    None if self.depth == 0 => Ok(None),
+                    None => {
+                        self.position = State::ChildrenEnded;
+                        Ok(Some(Event::EndChildren))
+                    }
+                }
+            }
+            State::Content => Ok(Some(
+                match run_parser_on(&mut self.state, parse_node_event)? {
+                    InternalNodeEvent::Argument(argument) => Event::Argument(argument),
+                    InternalNodeEvent::Property(property) => Event::Property(property),
+                    InternalNodeEvent::Children => {
+                        self.depth += 1;
+                        self.position = State::Nodes;
+                        Event::StartChildren
+                    }
+                    InternalNodeEvent::End => {
+                        self.position = State::Nodes;
+                        Event::EndNode
+                    }
+                },
+            )),
+        }
+    }
+}
+
+impl<'i, E> Iterator for Events<'i, E>
+where
+    E: ParseError<&'i str>,
+    E: TagError<&'i str, &'static str>,
+    E: FromExternalError<&'i str, CharTryFromError>,
+    E: FromExternalError<&'i str, BoundsError>,
+    E: ContextError<&'i str, &'static str>,
+{
+    type Item = Result<Event<'i>, NomErr<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl<'i, E> std::iter::FusedIterator for Events<'i, E>
+where
+    E: ParseError<&'i str>,
+    E: TagError<&'i str, &'static str>,
+    E: FromExternalError<&'i str, CharTryFromError>,
+    E: FromExternalError<&'i str, BoundsError>,
+    E: ContextError<&'i str, &'static str>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{annotation::GenericAnnotated, number::KdlNumber, value::KdlValue};
+
+    fn string(s: &str) -> KdlString<'_> {
+        KdlString::from_borrowed(s)
+    }
+
+    fn events(input: &str) -> Vec<(usize, Event<'_>)> {
+        let mut events = Events::<()>::new(input);
+        let mut collected = Vec::new();
+
+        while let Some(event) = events.next_event().expect("parse error") {
+            collected.push((events.depth(), event));
+        }
+
+        collected
+    }
+
+    #[test]
+    fn nested_document() {
+        let content = r#"
+        (config)server "main" port=8080 {
+            tls
+            route (path)"/" { handler "index"; }
+        }
+        last
+        "#;
+
+        assert_eq!(
+            events(content),
+            [
+                (
+                    0,
+                    Event::StartNode {
+                        name: string("server"),
+                        annotation: Some(string("config")),
+                    }
+                ),
+                (
+                    0,
+                    Event::Argument(GenericAnnotated {
+                        annotation: None,
+                        item: KdlValue::String(string("main")),
+                    })
+                ),
+                (
+                    0,
+                    Event::Property(Property {
+                        key: string("port"),
+                        value: GenericAnnotated {
+                            annotation: None,
+                            item: KdlValue::Number(KdlNumber::Unsigned(8080)),
+                        },
+                    })
+                ),
+                (1, Event::StartChildren),
+                (
+                    1,
+                    Event::StartNode {
+                        name: string("tls"),
+                        annotation: None,
+                    }
+                ),
+                (1, Event::EndNode),
+                (
+                    1,
+                    Event::StartNode {
+                        name: string("route"),
+                        annotation: None,
+                    }
+                ),
+                (
+                    1,
+                    Event::Argument(GenericAnnotated {
+                        annotation: Some(string("path")),
+                        item: KdlValue::String(string("/")),
+                    })
+                ),
+                (2, Event::StartChildren),
+                (
+                    2,
+                    Event::StartNode {
+                        name: string("handler"),
+                        annotation: None,
+                    }
+                ),
+                (
+                    2,
+                    Event::Argument(GenericAnnotated {
+                        annotation: None,
+                        item: KdlValue::String(string("index")),
+                    })
+                ),
+                (2, Event::EndNode),
+                (2, Event::EndChildren),
+                (1, Event::EndNode),
+                (1, Event::EndChildren),
+                (0, Event::EndNode),
+                (
+                    0,
+                    Event::StartNode {
+                        name: string("last"),
+                        annotation: None,
+                    }
+                ),
+                (0, Event::EndNode),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_document() {
+        assert_eq!(events("  // just a comment\n"), []);
+    }
+
+    #[test]
+    fn error_is_fused() {
+        let mut events = Events::<()>::new("a {");
+
+        let results: Vec<_> = events.by_ref().collect();
+        assert!(matches!(results.last(), Some(Err(_))));
+        assert!(events.next().is_none());
+    }
+}
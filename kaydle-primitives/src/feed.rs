@@ -0,0 +1,763 @@
+/*!
+An incremental parser for KDL documents that arrive in pieces, such as over a
+socket or a pipe. A [`NodeFeeder`] accepts arbitrary chunks of bytes, and
+returns the text of each top-level node as soon as the node is complete. The
+text of each node is itself a valid KDL document, which can be parsed with
+any of the other tools in this crate (or deserialized with `kaydle`).
+
+The feeder finds the end of each node with the [lexer][crate::lexer]: a node
+ends at a newline or semicolon outside of any children block, or at the `}`
+that closes its children. Tokens are only trusted once something after them
+shows that they can't grow any further (a half-received `tr` might become
+`true`, and an unterminated string might swallow the next newline), and the
+feeder remembers how far it trusts the buffer, so each byte is usually only
+scanned once, no matter how small the chunks are. Each complete node is then
+parsed once, to check that it's valid KDL.
+
+Because a feeder buffers an incomplete node until it's finished, it checks
+the buffer against a set of [`Limits`] as it scans, so that a peer can't (for instance)
+send an endless string or endlessly nested children. Note that
+[`max_nodes`][Limits::max_nodes] applies to each top-level node (including
+its descendants), rather than to the whole stream.
+
+```
+use kaydle_primitives::feed::NodeFeeder;
+
+let mut feeder = NodeFeeder::new();
+
+feeder.feed(b"first 1\nsecond {\n").unwrap();
+assert_eq!(feeder.next_node().unwrap().as_deref(), Some("first 1\n"));
+assert_eq!(feeder.next_node().unwrap(), None);
+
+feeder.feed(b"    child\n}\nthird").unwrap();
+assert_eq!(
+    feeder.next_node().unwrap().as_deref(),
+    Some("second {\n    child\n}")
+);
+
+// The last node is only terminated by the end of the input
+assert_eq!(feeder.next_node().unwrap(), None);
+assert_eq!(feeder.finish().unwrap().as_deref(), Some("\nthird"));
+```
+*/
+
+use std::{error::Error, fmt, str};
+
+use nom::error::{ErrorKind, FromExternalError, ParseError};
+use nom_supreme::{context::ContextError, tag::TagError};
+
+use crate::{
+    events::Events,
+    lexer::{tokenize, TokenKind},
+    limits::{LimitError, LimitState, Limits},
+    whitespace::{is_newline, is_plain_whitespace},
+};
+
+/// An error from a [`NodeFeeder`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedError {
+    /// The input wasn't valid UTF-8
+    InvalidUtf8 {
+        /// The offset in the stream of the invalid bytes
+        offset: usize,
+    },
+
+    /// The input wasn't valid KDL
+    Syntax {
+        /// The offset in the stream of the invalid input
+        offset: usize,
+    },
+
+    /// The input exceeded one of the feeder's [`Limits`]. The offset in the
+    /// error is the offset in the stream.
+    Limit(LimitError),
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FeedError::InvalidUtf8 { offset } => {
+                write!(f, "invalid UTF-8 at byte offset {}", offset)
+            }
+            FeedError::Syntax { offset } => write!(f, "invalid KDL at byte offset {}", offset),
+            FeedError::Limit(ref err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for FeedError {}
+
+/// Parse error used to validate a complete node. Tracks how far the parse
+/// got.
+#[derive(Debug, Clone, Copy)]
+struct ProbeError {
+    /// The length of the input remaining at the furthest error
+    remaining: usize,
+}
+
+impl ParseError<&str> for ProbeError {
+    fn from_error_kind(input: &str, _kind: ErrorKind) -> Self {
+        Self {
+            remaining: input.len(),
+        }
+    }
+
+    fn append(_input: &str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        Self {
+            remaining: self.remaining.min(other.remaining),
+        }
+    }
+}
+
+impl TagError<&str, &'static str> for ProbeError {
+    fn from_tag(input: &str, _tag: &'static str) -> Self {
+        Self::from_error_kind(input, ErrorKind::Tag)
+    }
+}
+
+impl<X> FromExternalError<&str, X> for ProbeError {
+    fn from_external_error(input: &str, kind: ErrorKind, _error: X) -> Self {
+        Self::from_error_kind(input, kind)
+    }
+}
+
+impl ContextError<&str, &'static str> for ProbeError {
+    fn add_context(_location: &str, _ctx: &'static str, other: Self) -> Self {
+        other
+    }
+}
+
+/// How far the feeder has scanned into the buffer, and what it found there.
+#[derive(Debug, Clone)]
+struct Scan {
+    /// The offset in the buffer of the first token that might still change
+    position: usize,
+
+    /// The nesting depth of children blocks at `position`
+    depth: usize,
+
+    /// True if a top-level node has started before `position`
+    in_node: bool,
+
+    /// The offset of a `\` that might turn out to be an escaped newline, if
+    /// only whitespace and comments have followed it
+    backslash: Option<usize>,
+
+    /// The offset of an `r` or `r#` that might turn out to start a raw
+    /// string, which could swallow anything that follows it
+    raw: Option<usize>,
+
+    limits: LimitState,
+}
+
+impl Scan {
+    fn new(limits: Limits) -> Self {
+        Self {
+            position: 0,
+            depth: 0,
+            in_node: false,
+            backslash: None,
+            raw: None,
+            limits: LimitState::new(limits),
+        }
+    }
+}
+
+/// What the scan was waiting for when it ran out of input. If the next chunk
+/// can't possibly provide it, the buffer doesn't need to be scanned again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    /// The closing `"` of a string or raw string, which is followed by this
+    /// many `#`s
+    Quote { hashes: usize },
+
+    /// The end of a block comment
+    CommentEnd,
+
+    /// The newline after a line comment
+    Newline,
+
+    /// Anything other than more whitespace
+    NonWhitespace,
+
+    /// Anything at all
+    Anything,
+}
+
+/// The state of an unfinished scan, waiting for more input
+#[derive(Debug, Clone, Copy)]
+struct Stall {
+    wait: Wait,
+
+    /// The offset in the buffer of the unfinished token
+    start: usize,
+
+    /// The length of the buffer when the scan ran out
+    end: usize,
+}
+
+/// Check if an identifier is `r` or `r#`, `r##`, etc, which starts a raw
+/// string if it's followed by a `"`
+fn is_raw_prefix(text: &str) -> bool {
+    text.strip_prefix('r')
+        .is_some_and(|hashes| hashes.chars().all(|c| c == '#'))
+}
+
+/// The outcome of probing the buffer for a node
+enum Probe {
+    /// There's a complete node in the first `len` bytes of the buffer
+    Node { len: usize },
+
+    /// There isn't a complete node yet
+    Incomplete,
+}
+
+/// Incremental parser that splits a stream of KDL into top-level nodes. See
+/// the [module docs][self] for details.
+#[derive(Debug, Clone)]
+pub struct NodeFeeder {
+    /// Input that hasn't been returned as a node yet
+    buffer: String,
+
+    /// Trailing bytes of a UTF-8 character that was split between chunks
+    partial: Vec<u8>,
+
+    /// The offset in the stream of the start of `buffer`
+    offset: usize,
+
+    limits: Limits,
+
+    /// The scan of the node at the start of `buffer`
+    scan: Scan,
+
+    /// Set if the last scan ran out of input in the middle of a token
+    stall: Option<Stall>,
+}
+
+impl Default for NodeFeeder {
+    fn default() -> Self {
+        Self::with_limits(Limits::default())
+    }
+}
+
+impl NodeFeeder {
+    /// Create a new, empty feeder, with the default [`Limits`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new, empty feeder, with custom [`Limits`].
+    pub fn with_limits(limits: Limits) -> Self {
+        Self {
+            buffer: String::new(),
+            partial: Vec::new(),
+            offset: 0,
+            limits,
+            scan: Scan::new(limits),
+            stall: None,
+        }
+    }
+
+    /// Add a chunk of input to the feeder. The chunk can be split anywhere,
+    /// including in the middle of a UTF-8 character.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), FeedError> {
+        let owned;
+        let mut bytes = chunk;
+
+        if !self.partial.is_empty() {
+            owned = [self.partial.as_slice(), chunk].concat();
+            bytes = &owned;
+        }
+
+        let valid = match str::from_utf8(bytes) {
+            Ok(valid) => valid,
+            // The end of the chunk is an incomplete character
+            Err(err) if err.error_len().is_none() => {
+                str::from_utf8(&bytes[..err.valid_up_to()]).expect("prefix is valid")
+            }
+            Err(err) => {
+                return Err(FeedError::InvalidUtf8 {
+                    offset: self.offset + self.buffer.len() + err.valid_up_to(),
+                })
+            }
+        };
+
+        self.buffer.push_str(valid);
+        self.partial = bytes[valid.len()..].to_vec();
+        Ok(())
+    }
+
+    /// Get the next complete top-level node, if there is one. The returned
+    /// text includes any comments and whitespace before the node, as well as
+    /// its terminator.
+    ///
+    /// Returns `Ok(None)` if more input is needed. A node terminated only by
+    /// the end of the input can't be detected until the input actually ends,
+    /// so it's returned by [`finish`][Self::finish] instead.
+    pub fn next_node(&mut self) -> Result<Option<String>, FeedError> {
+        match self.probe()? {
+            Probe::Incomplete => Ok(None),
+            Probe::Node { len } => {
+                self.validate(len)?;
+
+                let node: String = self.buffer.drain(..len).collect();
+                self.offset += len;
+                self.scan = Scan::new(self.limits);
+                self.stall = None;
+                Ok(Some(node))
+            }
+        }
+    }
+
+    /// Signal the end of the input, and get whatever is left in the buffer,
+    /// if it contains any nodes. Call [`next_node`][Self::next_node] until it
+    /// returns `None` before calling this.
+    pub fn finish(self) -> Result<Option<String>, FeedError> {
+        if !self.partial.is_empty() {
+            return Err(FeedError::InvalidUtf8 {
+                offset: self.offset + self.buffer.len(),
+            });
+        }
+
+        // Everything after the scan position is final now
+        let mut limits = self.scan.limits.clone();
+        let position = self.scan.position;
+
+        for token in tokenize(&self.buffer[position..]) {
+            limits
+                .token(token.kind, token.text, position + token.span.start)
+                .map_err(|err| FeedError::Limit(err.shifted(self.offset)))?;
+        }
+
+        let empty = self.validate(self.buffer.len())?;
+        Ok((!empty).then_some(self.buffer))
+    }
+
+    /// Check that the first `len` bytes of the buffer are valid KDL, and
+    /// return true if they don't contain any nodes.
+    fn validate(&self, len: usize) -> Result<bool, FeedError> {
+        let mut events = Events::<ProbeError>::new(&self.buffer[..len]);
+        let mut empty = true;
+
+        loop {
+            match events.next_event() {
+                Ok(Some(_)) => empty = false,
+                Ok(None) => return Ok(empty),
+                Err(nom::Err::Incomplete(_)) => unreachable!("parsers are complete"),
+                Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+                    return Err(FeedError::Syntax {
+                        offset: self.offset + len - err.remaining,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Continue scanning the buffer, looking for the end of the node at the
+    /// start of it.
+    fn probe(&mut self) -> Result<Probe, FeedError> {
+        if let Some(stall) = self.stall {
+            if self.still_stalled(stall)? {
+                self.stall = Some(Stall {
+                    end: self.buffer.len(),
+                    ..stall
+                });
+                return Ok(Probe::Incomplete);
+            }
+        }
+
+        let offset = self.offset;
+        let limit_error = |err: LimitError| FeedError::Limit(err.shifted(offset));
+
+        let position = self.scan.position;
+        let mut scan = self.scan.clone();
+        let mut last = None;
+
+        for token in tokenize(&self.buffer[position..]) {
+            let start = position + token.span.start;
+            let end = position + token.span.end;
+            let at_end = end == self.buffer.len();
+
+            scan.limits
+                .token(token.kind, token.text, start)
+                .map_err(limit_error)?;
+
+            if scan.raw.is_some() {
+                match token.kind {
+                    // The raw string finally lexed properly
+                    TokenKind::RawString => scan.raw = None,
+                    _ => {
+                        last = Some((token.kind, token.text, start));
+                        continue;
+                    }
+                }
+            }
+
+            match token.kind {
+                TokenKind::OpenBrace => scan.depth += 1,
+                TokenKind::CloseBrace => scan.depth = scan.depth.saturating_sub(1),
+                _ => {}
+            }
+
+            match token.kind {
+                TokenKind::Whitespace
+                | TokenKind::LineComment
+                | TokenKind::BlockComment
+                | TokenKind::Newline
+                | TokenKind::Semicolon
+                | TokenKind::Escline
+                | TokenKind::CloseBrace => {}
+                _ => scan.in_node = true,
+            }
+
+            match token.kind {
+                TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment => {}
+                TokenKind::Error if token.text == "\\" => scan.backslash = Some(start),
+                TokenKind::Identifier
+                    if is_raw_prefix(token.text) && self.buffer[end..].starts_with('"') =>
+                {
+                    scan.backslash = None;
+                    scan.raw = Some(start);
+                }
+                _ => scan.backslash = None,
+            }
+
+            // A token might still grow if it's at the end of the buffer;
+            // only a few can't.
+            let finished = match token.kind {
+                TokenKind::OpenParen
+                | TokenKind::CloseParen
+                | TokenKind::OpenBrace
+                | TokenKind::CloseBrace
+                | TokenKind::Equals
+                | TokenKind::Semicolon
+                | TokenKind::SlashDash
+                | TokenKind::BlockComment => true,
+                // A `\r` might be followed by a `\n`, and a line comment in
+                // an escaped newline might not be finished
+                TokenKind::Newline | TokenKind::Escline => {
+                    !at_end || (token.text.ends_with(is_newline) && !token.text.ends_with('\r'))
+                }
+                TokenKind::Whitespace | TokenKind::LineComment => !at_end,
+                _ => false,
+            };
+
+            // Only delimiters are trusted, since they also end whatever came
+            // before them, and nothing is trusted after a `\` that might
+            // still become an escaped newline.
+            if !finished || scan.backslash.is_some() {
+                last = Some((token.kind, token.text, start));
+                continue;
+            }
+
+            scan.position = end;
+            self.scan = scan.clone();
+            last = None;
+
+            let node_end = match token.kind {
+                TokenKind::Newline | TokenKind::Semicolon => scan.depth == 0 && scan.in_node,
+                TokenKind::CloseBrace => scan.depth == 0,
+                _ => false,
+            };
+
+            if node_end {
+                return Ok(Probe::Node { len: end });
+            }
+        }
+
+        self.stall = last.map(|(kind, text, start)| {
+            let (wait, start) = match (kind, scan.raw) {
+                (_, Some(raw)) => {
+                    let hashes = self.buffer[raw..].find('"').map_or(0, |len| len - 1);
+                    (Wait::Quote { hashes }, raw)
+                }
+                (TokenKind::Error, _) if text.starts_with('"') => {
+                    (Wait::Quote { hashes: 0 }, start)
+                }
+                (TokenKind::Error, _) if text.starts_with("/*") => (Wait::CommentEnd, start),
+                (TokenKind::LineComment, _) => (Wait::Newline, start),
+                (TokenKind::Whitespace, _) => (Wait::NonWhitespace, start),
+                _ => (Wait::Anything, start),
+            };
+
+            Stall {
+                wait,
+                start,
+                end: self.buffer.len(),
+            }
+        });
+
+        // A possible raw string is held back until it's closed, so make sure
+        // that it can't grow without bound.
+        if let Some(start) = scan.raw {
+            self.check_string(start)?;
+        }
+
+        Ok(Probe::Incomplete)
+    }
+
+    /// Check if the input that arrived since the scan stalled still can't
+    /// finish the token it stalled on, so there's no need to scan again.
+    fn still_stalled(&self, stall: Stall) -> Result<bool, FeedError> {
+        let new = &self.buffer[stall.end..];
+
+        let stalled = match stall.wait {
+            // The quote might have arrived before the `#`s that close a raw
+            // string
+            Wait::Quote { hashes } => !self.buffer.as_bytes()[stall.end - hashes..].contains(&b'"'),
+            Wait::CommentEnd => !self.buffer.as_bytes()[stall.end.saturating_sub(1)..]
+                .windows(2)
+                .any(|pair| pair == b"*/" || pair == b"/*"),
+            Wait::Newline => !new.contains(is_newline),
+            Wait::NonWhitespace => new.chars().all(is_plain_whitespace),
+            Wait::Anything => new.is_empty(),
+        };
+
+        if stalled && matches!(stall.wait, Wait::Quote { .. }) {
+            self.check_string(stall.start)?;
+        }
+
+        Ok(stalled)
+    }
+
+    /// Check the length of an unterminated string that runs from `start` to
+    /// the end of the buffer
+    fn check_string(&self, start: usize) -> Result<(), FeedError> {
+        let limit = self.limits.max_string_length;
+
+        if self.buffer.len() - start > limit {
+            Err(FeedError::Limit(LimitError::StringLength {
+                limit,
+                offset: self.offset + start,
+            }))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed the input one byte at a time, collecting nodes as they appear
+    fn feed_bytewise(input: &str) -> Result<Vec<String>, FeedError> {
+        let mut feeder = NodeFeeder::new();
+        let mut nodes = Vec::new();
+
+        for byte in input.as_bytes() {
+            feeder.feed(std::slice::from_ref(byte))?;
+
+            while let Some(node) = feeder.next_node()? {
+                nodes.push(node);
+            }
+        }
+
+        nodes.extend(feeder.finish()?);
+        Ok(nodes)
+    }
+
+    #[test]
+    fn bytewise() {
+        let input = "a 1 \"two\\nlines\" r#\"raw \"quoted\" string\"#\n\
+                     (t)b key=true {\n    /* comment\n */ child \"ü\"; other\n}\n\
+                     c 10; d null // end\n\
+                     e \\\n    \"continued\"\n\
+                     f";
+
+        assert_eq!(
+            feed_bytewise(input).expect("failed to feed"),
+            [
+                "a 1 \"two\\nlines\" r#\"raw \"quoted\" string\"#\n",
+                "(t)b key=true {\n    /* comment\n */ child \"ü\"; other\n}",
+                "\nc 10;",
+                " d null // end\n",
+                "e \\\n    \"continued\"\n",
+                "f",
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_keyword() {
+        let mut feeder = NodeFeeder::new();
+
+        feeder.feed(b"a tr").unwrap();
+        assert_eq!(feeder.next_node().unwrap(), None);
+
+        feeder.feed(b"ue\n").unwrap();
+        assert_eq!(feeder.next_node().unwrap().as_deref(), Some("a true\n"));
+    }
+
+    #[test]
+    fn syntax_error() {
+        let mut feeder = NodeFeeder::new();
+
+        feeder.feed(b"a 1\nb [oops]\nc 2\n").unwrap();
+        assert_eq!(feeder.next_node().unwrap().as_deref(), Some("a 1\n"));
+        assert_eq!(feeder.next_node(), Err(FeedError::Syntax { offset: 6 }));
+    }
+
+    #[test]
+    fn unterminated_at_finish() {
+        let mut feeder = NodeFeeder::new();
+
+        feeder.feed(b"a \"unterminated").unwrap();
+        assert_eq!(feeder.next_node().unwrap(), None);
+        assert_eq!(feeder.finish(), Err(FeedError::Syntax { offset: 15 }));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut feeder = NodeFeeder::new();
+
+        feeder.feed(b"a 1\n").unwrap();
+        assert_eq!(
+            feeder.feed(b"b \xFF"),
+            Err(FeedError::InvalidUtf8 { offset: 6 })
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let mut feeder = NodeFeeder::with_limits(Limits {
+            max_depth: 2,
+            ..Limits::default()
+        });
+
+        feeder.feed(b"a 1\nb { c { d {").unwrap();
+        assert_eq!(feeder.next_node().unwrap().as_deref(), Some("a 1\n"));
+        assert_eq!(
+            feeder.next_node(),
+            Err(FeedError::Limit(LimitError::Depth {
+                limit: 2,
+                offset: 14
+            }))
+        );
+    }
+
+    #[test]
+    fn held_back_tokens() {
+        // A raw string can contain anything, including delimiters
+        let input = "a r#\"x\" }\n; y\"# 1\nb \\ // escaped\n    2\nc \\\n";
+
+        assert_eq!(
+            feed_bytewise(input).expect("failed to feed"),
+            [
+                "a r#\"x\" }\n; y\"# 1\n",
+                "b \\ // escaped\n    2\n",
+                "c \\\n"
+            ]
+        );
+
+        // A `\` that isn't followed by a newline is an error
+        let mut feeder = NodeFeeder::new();
+        feeder.feed(b"a \\ ").unwrap();
+        assert_eq!(feeder.next_node().unwrap(), None);
+        feeder.feed(b"b\n").unwrap();
+        assert_eq!(feeder.next_node(), Err(FeedError::Syntax { offset: 2 }));
+    }
+
+    #[test]
+    fn large_node() {
+        // Each byte should only be scanned a constant number of times, so
+        // this shouldn't take long, even when fed one byte at a time.
+        let input = format!(
+            "big \"{}\" {} {{\n{}}}\nsmall\n",
+            "x".repeat(200_000),
+            "1 ".repeat(50_000),
+            "    child \"arg\" key=1\n".repeat(5_000),
+        );
+
+        let nodes = feed_bytewise(&input).expect("failed to feed");
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].len(), input.len() - "\nsmall\n".len());
+        assert_eq!(nodes[1], "\nsmall\n");
+    }
+
+    #[test]
+    fn unterminated_string_limit() {
+        let mut feeder = NodeFeeder::with_limits(Limits {
+            max_string_length: 10,
+            ..Limits::default()
+        });
+
+        feeder.feed(b"a 1\nb \"").unwrap();
+        assert_eq!(feeder.next_node().unwrap().as_deref(), Some("a 1\n"));
+        assert_eq!(feeder.next_node().unwrap(), None);
+
+        for _ in 0..9 {
+            feeder.feed(b"x").unwrap();
+            assert_eq!(feeder.next_node().unwrap(), None);
+        }
+
+        feeder.feed(b"x").unwrap();
+        assert_eq!(
+            feeder.next_node(),
+            Err(FeedError::Limit(LimitError::StringLength {
+                limit: 10,
+                offset: 6
+            }))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket_pair() {
+        use std::{
+            io::{Read, Write},
+            os::unix::net::UnixStream,
+            thread,
+        };
+
+        let (mut writer, mut reader) = UnixStream::pair().expect("failed to create socket pair");
+
+        let sender = thread::spawn(move || {
+            let messages: [&[u8]; 5] = [
+                b"request id=1 ",
+                b"path=\"/\"\nrequest id=2 {\n    hea",
+                b"der \"accept\" \"*/*\"\n}\nrequest id=3",
+                b"\n// done\n",
+                b"shutdown",
+            ];
+
+            for message in messages {
+                writer.write_all(message).expect("failed to write");
+                writer.flush().expect("failed to flush");
+            }
+        });
+
+        let mut feeder = NodeFeeder::new();
+        let mut nodes = Vec::new();
+        let mut buffer = [0; 7];
+
+        loop {
+            let len = reader.read(&mut buffer).expect("failed to read");
+            if len == 0 {
+                break;
+            }
+
+            feeder.feed(&buffer[..len]).expect("invalid input");
+            while let Some(node) = feeder.next_node().expect("invalid input") {
+                nodes.push(node);
+            }
+        }
+
+        nodes.extend(feeder.finish().expect("invalid input"));
+        sender.join().expect("sender panicked");
+
+        assert_eq!(
+            nodes,
+            [
+                "request id=1 path=\"/\"\n",
+                "request id=2 {\n    header \"accept\" \"*/*\"\n}",
+                "\nrequest id=3\n",
+                "// done\nshutdown",
+            ]
+        );
+    }
+}
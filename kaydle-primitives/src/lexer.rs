@@ -0,0 +1,373 @@
+/*!
+A lexer that splits a KDL document into classified [`Token`]s, each with its
+byte span in the input. It's intended for tooling like syntax highlighters,
+which need to see every part of the document (including whitespace and
+comments) and which need to keep going in the face of invalid input.
+
+The lexer never fails: anything it doesn't recognize is returned as a
+[`TokenKind::Error`] token, and lexing resumes afterwards. The spans of the
+tokens are contiguous and together cover the entire input.
+
+```
+use kaydle_primitives::lexer::{tokenize, TokenKind};
+
+let kinds: Vec<TokenKind> = tokenize("node key=1 // hi")
+    .map(|token| token.kind)
+    .collect();
+
+assert_eq!(
+    kinds,
+    [
+        TokenKind::Identifier,
+        TokenKind::Whitespace,
+        TokenKind::Identifier,
+        TokenKind::Equals,
+        TokenKind::Number,
+        TokenKind::Whitespace,
+        TokenKind::LineComment,
+    ]
+);
+```
+*/
+
+use std::ops::Range;
+
+use nom::Parser;
+
+use crate::{
+    number::parse_number,
+    string::{parse_bare_identifier, parse_escaped_string, parse_raw_string},
+    whitespace::{
+        is_newline, is_plain_whitespace, parse_block_comment, parse_escaped_endline, parse_newline,
+    },
+};
+
+/// The kind of a [`Token`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A bare identifier, like `node`
+    Identifier,
+
+    /// A quoted string with escapes, like `"hello\n"`
+    String,
+
+    /// A raw string, like `r#"hello"#`
+    RawString,
+
+    /// A number, like `10`, `-1.5e3`, or `0xFF`
+    Number,
+
+    /// One of the keywords `true`, `false`, or `null`
+    Keyword,
+
+    /// The `(` that opens a type annotation
+    OpenParen,
+
+    /// The `)` that closes a type annotation
+    CloseParen,
+
+    /// The `{` that opens a children block
+    OpenBrace,
+
+    /// The `}` that closes a children block
+    CloseBrace,
+
+    /// The `=` in a property
+    Equals,
+
+    /// A `;` node terminator
+    Semicolon,
+
+    /// A `// comment`, not including the newline that ends it
+    LineComment,
+
+    /// A `/* comment */`, which may be nested
+    BlockComment,
+
+    /// A `/-` slashdash, which comments out the following node, argument,
+    /// property, or children block
+    SlashDash,
+
+    /// An escaped newline (a `\` followed by a newline or line comment)
+    Escline,
+
+    /// A single newline
+    Newline,
+
+    /// Plain, non-newline whitespace
+    Whitespace,
+
+    /// Something that isn't valid KDL, such as an unterminated string or an
+    /// unexpected character
+    Error,
+}
+
+/// A single token from a KDL document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'i> {
+    /// The kind of this token
+    pub kind: TokenKind,
+
+    /// The byte range of this token in the input
+    pub span: Range<usize>,
+
+    /// The text of this token, which is `&input[span]`
+    pub text: &'i str,
+}
+
+/// Create a [`Lexer`] over a KDL document.
+pub fn tokenize(input: &str) -> Lexer<'_> {
+    Lexer::new(input)
+}
+
+/// Iterator over the [`Token`]s in a KDL document. See the
+/// [module docs][self] for details.
+#[derive(Debug, Clone)]
+pub struct Lexer<'i> {
+    input: &'i str,
+
+    /// The byte offset of the next token in `input`
+    position: usize,
+}
+
+impl<'i> Lexer<'i> {
+    /// Create a new lexer over a KDL document.
+    pub fn new(input: &'i str) -> Self {
+        Self { input, position: 0 }
+    }
+}
+
+/// Run a recognizer, returning the length of the recognized input
+fn recognize_len<'i, O>(
+    input: &'i str,
+    mut parser: impl Parser<&'i str, O, ()>,
+) -> Result<usize, nom::Err<()>> {
+    parser
+        .parse(input)
+        .map(|(tail, _)| input.len() - tail.len())
+}
+
+/// Find the length and kind of the token at the start of `input`, which must
+/// be non-empty.
+fn next_token(input: &str) -> (usize, TokenKind) {
+    let mut chars = input.chars();
+    let first = chars.next().expect("input must be non-empty");
+    let second = chars.next();
+
+    // Several constructs, if they're unterminated, swallow the rest of the
+    // input, just like they would in a real parse.
+    let or_rest = |len: Result<usize, nom::Err<()>>, kind| match len {
+        Ok(len) => (len, kind),
+        Err(_) => (input.len(), TokenKind::Error),
+    };
+
+    match (first, second) {
+        ('/', Some('/')) => (
+            input.find(is_newline).unwrap_or(input.len()),
+            TokenKind::LineComment,
+        ),
+        ('/', Some('*')) => or_rest(
+            recognize_len(input, parse_block_comment),
+            TokenKind::BlockComment,
+        ),
+        ('/', Some('-')) => (2, TokenKind::SlashDash),
+        ('\\', _) => match recognize_len(input, parse_escaped_endline) {
+            Ok(len) => (len, TokenKind::Escline),
+            Err(_) => (1, TokenKind::Error),
+        },
+        ('"', _) => or_rest(
+            recognize_len(input, parse_escaped_string::<(), _>),
+            TokenKind::String,
+        ),
+        // `r#` can also start a bare identifier, like `r#foo`, so if this
+        // isn't a raw string, it's lexed like any other identifier.
+        ('r', Some('"' | '#')) => match recognize_len(input, parse_raw_string) {
+            Ok(len) => (len, TokenKind::RawString),
+            Err(_) => identifier(input, first),
+        },
+        ('(', _) => (1, TokenKind::OpenParen),
+        (')', _) => (1, TokenKind::CloseParen),
+        ('{', _) => (1, TokenKind::OpenBrace),
+        ('}', _) => (1, TokenKind::CloseBrace),
+        ('=', _) => (1, TokenKind::Equals),
+        (';', _) => (1, TokenKind::Semicolon),
+        (c, _) if is_newline(c) => (
+            recognize_len(input, parse_newline).unwrap_or(c.len_utf8()),
+            TokenKind::Newline,
+        ),
+        (c, _) if is_plain_whitespace(c) => (
+            input
+                .find(|c| !is_plain_whitespace(c))
+                .unwrap_or(input.len()),
+            TokenKind::Whitespace,
+        ),
+        (c, _) => match recognize_len(input, parse_number::<(), ()>) {
+            Ok(len) => (len, TokenKind::Number),
+            Err(_) => identifier(input, c),
+        },
+    }
+}
+
+/// Lex a bare identifier or keyword at the start of `input`, which starts
+/// with `first`. If it isn't an identifier, only `first` is an error.
+fn identifier(input: &str, first: char) -> (usize, TokenKind) {
+    match recognize_len(input, parse_bare_identifier) {
+        Ok(len) => match &input[..len] {
+            "true" | "false" | "null" => (len, TokenKind::Keyword),
+            _ => (len, TokenKind::Identifier),
+        },
+        Err(_) => (first.len_utf8(), TokenKind::Error),
+    }
+}
+
+impl<'i> Iterator for Lexer<'i> {
+    type Item = Token<'i>;
+
+    fn next(&mut self) -> Option<Token<'i>> {
+        let input = &self.input[self.position..];
+
+        if input.is_empty() {
+            return None;
+        }
+
+        let (len, kind) = next_token(input);
+        let span = self.position..self.position + len;
+        self.position = span.end;
+
+        Some(Token {
+            kind,
+            text: &self.input[span.clone()],
+            span,
+        })
+    }
+}
+
+impl std::iter::FusedIterator for Lexer<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<(TokenKind, &str)> {
+        let tokens: Vec<Token<'_>> = tokenize(input).collect();
+
+        // The spans must be contiguous and cover the entire input
+        let mut position = 0;
+        for token in &tokens {
+            assert_eq!(token.span.start, position);
+            assert_eq!(&input[token.span.clone()], token.text);
+            position = token.span.end;
+        }
+        assert_eq!(position, input.len());
+
+        tokens
+            .into_iter()
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn full_document() {
+        use TokenKind::*;
+
+        let input = "(t)node \"a\\n\" r#\"raw\"# -1.5 0xFF true /-key=null {\n\
+                     \tchild; /* block /* nested */ */ \\ // escline\n\
+                     }";
+
+        assert_eq!(
+            tokens(input),
+            [
+                (OpenParen, "("),
+                (Identifier, "t"),
+                (CloseParen, ")"),
+                (Identifier, "node"),
+                (Whitespace, " "),
+                (String, "\"a\\n\""),
+                (Whitespace, " "),
+                (RawString, "r#\"raw\"#"),
+                (Whitespace, " "),
+                (Number, "-1.5"),
+                (Whitespace, " "),
+                (Number, "0xFF"),
+                (Whitespace, " "),
+                (Keyword, "true"),
+                (Whitespace, " "),
+                (SlashDash, "/-"),
+                (Identifier, "key"),
+                (Equals, "="),
+                (Keyword, "null"),
+                (Whitespace, " "),
+                (OpenBrace, "{"),
+                (Newline, "\n"),
+                (Whitespace, "\t"),
+                (Identifier, "child"),
+                (Semicolon, ";"),
+                (Whitespace, " "),
+                (BlockComment, "/* block /* nested */ */"),
+                (Whitespace, " "),
+                (Escline, "\\ // escline\n"),
+                (CloseBrace, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_string() {
+        assert_eq!(
+            tokens("node \"abc\ndef"),
+            [
+                (TokenKind::Identifier, "node"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Error, "\"abc\ndef"),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_characters() {
+        assert_eq!(
+            tokens("a [b] \\c"),
+            [
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Error, "["),
+                (TokenKind::Identifier, "b"),
+                (TokenKind::Error, "]"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Error, "\\"),
+                (TokenKind::Identifier, "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_string_prefix_identifiers() {
+        assert_eq!(
+            tokens("r#x a {\n}"),
+            [
+                (TokenKind::Identifier, "r#x"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::OpenBrace, "{"),
+                (TokenKind::Newline, "\n"),
+                (TokenKind::CloseBrace, "}"),
+            ]
+        );
+
+        // An unterminated raw string isn't a raw string, so it's lexed the
+        // same way the parser would see it
+        assert_eq!(
+            tokens("r#\"abc"),
+            [(TokenKind::Identifier, "r#"), (TokenKind::Error, "\"abc"),]
+        );
+    }
+
+    #[test]
+    fn line_comment_at_eof() {
+        assert_eq!(
+            tokens("// comment"),
+            [(TokenKind::LineComment, "// comment")]
+        );
+    }
+}
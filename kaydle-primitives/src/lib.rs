@@ -7,6 +7,7 @@ probably not be using this crate.
 */
 
 #![deny(missing_docs)]
+#![forbid(unsafe_code)]
 
 use nom::{branch::alt, error::ParseError, IResult, Parser};
 use nom_supreme::{
@@ -15,9 +16,16 @@ use nom_supreme::{
 };
 
 pub mod annotation;
+pub mod emit;
+pub mod error;
+pub mod events;
+pub mod feed;
+pub mod lexer;
+pub mod limits;
 pub mod node;
 pub mod number;
 pub mod property;
+pub mod reserved;
 pub mod string;
 mod util;
 pub mod value;
@@ -0,0 +1,581 @@
+/*!
+Resource limits for parsing untrusted KDL documents.
+
+The parsers in `kaydle-primitives` are iterative, but some consumers (like
+`kaydle`'s serde deserializer) recurse once for each level of nested
+children, so a hostile document with deeply nested braces can overflow the
+stack. Such consumers can check [`Children::depth`] as they go. Similarly, a
+document might contain huge strings or numbers, or millions of nodes.
+[`Limits::check`] scans a document without recursing or allocating, and
+reports the first limit it exceeds, so that untrusted documents can be
+rejected before they're parsed for real.
+
+[`Children::depth`]: crate::node::Children::depth
+
+```
+use kaydle_primitives::limits::{LimitError, Limits};
+
+let limits = Limits {
+    max_depth: 2,
+    ..Limits::default()
+};
+
+assert!(limits.check("a { b { c; } }").is_ok());
+assert_eq!(
+    limits.check("a { b { c { d; } } }"),
+    Err(LimitError::Depth { limit: 2, offset: 10 }),
+);
+```
+*/
+
+use std::{error::Error, fmt};
+
+use crate::lexer::{tokenize, TokenKind};
+
+/// Limits on the size and shape of a KDL document. The [`Default`] limits
+/// are generous enough for any reasonable hand-written document, but small
+/// enough to avoid stack overflows and runaway resource usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum nesting depth of children blocks. Top-level nodes are at
+    /// depth 0, so a depth of 0 forbids children entirely. Defaults to 128.
+    pub max_depth: usize,
+
+    /// The maximum number of nodes, at any depth, in the whole document.
+    /// Defaults to 2<sup>20</sup>.
+    pub max_nodes: usize,
+
+    /// The maximum length, in bytes, of a string or identifier, including
+    /// any quotes and escapes. Defaults to 1 MiB.
+    pub max_string_length: usize,
+
+    /// The maximum length, in bytes, of a number literal. Defaults to 256.
+    pub max_number_length: usize,
+
+    /// The maximum nesting depth of `/* */` comments. Defaults to 128.
+    pub max_comment_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_nodes: 1 << 20,
+            max_string_length: 1 << 20,
+            max_number_length: 256,
+            max_comment_depth: 128,
+        }
+    }
+}
+
+/// A limit from [`Limits`] was exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    /// Children were nested too deeply
+    Depth {
+        /// The maximum depth
+        limit: usize,
+        /// The byte offset of the `{` that exceeded the limit
+        offset: usize,
+    },
+
+    /// There were too many nodes
+    NodeCount {
+        /// The maximum number of nodes
+        limit: usize,
+        /// The byte offset of the node that exceeded the limit
+        offset: usize,
+    },
+
+    /// A string or identifier was too long
+    StringLength {
+        /// The maximum length, in bytes
+        limit: usize,
+        /// The byte offset of the string
+        offset: usize,
+    },
+
+    /// A number literal was too long
+    NumberLength {
+        /// The maximum length, in bytes
+        limit: usize,
+        /// The byte offset of the number
+        offset: usize,
+    },
+
+    /// Block comments were nested too deeply
+    CommentDepth {
+        /// The maximum depth
+        limit: usize,
+        /// The byte offset of the outermost comment
+        offset: usize,
+    },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (what, limit, offset) = match *self {
+            LimitError::Depth { limit, offset } => ("children nested deeper than", limit, offset),
+            LimitError::NodeCount { limit, offset } => ("more nodes than", limit, offset),
+            LimitError::StringLength { limit, offset } => {
+                ("string longer than (bytes)", limit, offset)
+            }
+            LimitError::NumberLength { limit, offset } => {
+                ("number longer than (bytes)", limit, offset)
+            }
+            LimitError::CommentDepth { limit, offset } => {
+                ("comments nested deeper than", limit, offset)
+            }
+        };
+
+        write!(f, "{} {} at byte offset {}", what, limit, offset)
+    }
+}
+
+impl Error for LimitError {}
+
+impl LimitError {
+    /// The byte offset where the limit was exceeded.
+    pub fn offset(&self) -> usize {
+        match *self {
+            LimitError::Depth { offset, .. }
+            | LimitError::NodeCount { offset, .. }
+            | LimitError::StringLength { offset, .. }
+            | LimitError::NumberLength { offset, .. }
+            | LimitError::CommentDepth { offset, .. } => offset,
+        }
+    }
+
+    /// Move the offset of this error, for when the checked input was a
+    /// suffix of some larger input.
+    pub fn shifted(self, by: usize) -> Self {
+        match self {
+            LimitError::Depth { limit, offset } => LimitError::Depth {
+                limit,
+                offset: offset + by,
+            },
+            LimitError::NodeCount { limit, offset } => LimitError::NodeCount {
+                limit,
+                offset: offset + by,
+            },
+            LimitError::StringLength { limit, offset } => LimitError::StringLength {
+                limit,
+                offset: offset + by,
+            },
+            LimitError::NumberLength { limit, offset } => LimitError::NumberLength {
+                limit,
+                offset: offset + by,
+            },
+            LimitError::CommentDepth { limit, offset } => LimitError::CommentDepth {
+                limit,
+                offset: offset + by,
+            },
+        }
+    }
+}
+
+/// Find the deepest nesting of `/*` in a block comment
+fn comment_depth(comment: &str) -> usize {
+    let mut depth: usize = 0;
+    let mut max_depth = 0;
+    let mut bytes = comment.as_bytes();
+
+    while let Some(pair) = bytes.get(..2) {
+        bytes = match pair {
+            b"/*" => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+                &bytes[2..]
+            }
+            b"*/" => {
+                depth = depth.saturating_sub(1);
+                &bytes[2..]
+            }
+            _ => &bytes[1..],
+        };
+    }
+
+    max_depth
+}
+
+impl Limits {
+    /// Limits that allow anything. Only use these for trusted documents.
+    pub fn unlimited() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_nodes: usize::MAX,
+            max_string_length: usize::MAX,
+            max_number_length: usize::MAX,
+            max_comment_depth: usize::MAX,
+        }
+    }
+
+    /// Check that a document is within these limits. This is a fast, flat
+    /// scan of the document's tokens; it doesn't check that the document is
+    /// valid KDL, so invalid documents that are within the limits will still
+    /// fail to parse later.
+    pub fn check(&self, input: &str) -> Result<(), LimitError> {
+        let mut state = LimitState::new(*self);
+
+        tokenize(input).try_for_each(|token| state.token(token.kind, token.text, token.span.start))
+    }
+}
+
+/// Checks a document against [`Limits`] one top-level node at a time, for
+/// documents that are parsed one node at a time. The node count carries over
+/// from one node to the next, so [`max_nodes`][Limits::max_nodes] applies to
+/// all of the nodes checked so far.
+///
+/// ```
+/// use kaydle_primitives::limits::{LimitError, Limits, NodeChecker};
+///
+/// let mut checker = NodeChecker::new(Limits {
+///     max_nodes: 2,
+///     ..Limits::default()
+/// });
+///
+/// // Only the first node is checked
+/// assert_eq!(checker.check_node("a\nb { c; }"), Ok(()));
+/// assert_eq!(
+///     checker.check_node("b { c; }"),
+///     Err(LimitError::NodeCount { limit: 2, offset: 4 })
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct NodeChecker {
+    state: LimitState,
+}
+
+impl NodeChecker {
+    /// Create a new checker, which hasn't seen any nodes yet
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            state: LimitState::new(limits),
+        }
+    }
+
+    /// Check the first top-level node in `input` (and any whitespace and
+    /// comments before it), up to and including its terminator. The offsets
+    /// in errors are relative to the start of `input`.
+    pub fn check_node(&mut self, input: &str) -> Result<(), LimitError> {
+        let mut started = false;
+
+        for token in tokenize(input) {
+            self.state.token(token.kind, token.text, token.span.start)?;
+
+            match token.kind {
+                TokenKind::Newline | TokenKind::Semicolon if started && self.state.depth == 0 => {
+                    break
+                }
+                TokenKind::CloseBrace if self.state.depth == 0 => break,
+                TokenKind::Whitespace
+                | TokenKind::Newline
+                | TokenKind::Semicolon
+                | TokenKind::LineComment
+                | TokenKind::BlockComment
+                | TokenKind::Escline => {}
+                _ => started = true,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of a [`Limits::check`] scan, so that a document can be checked
+/// one token at a time as it arrives.
+#[derive(Debug, Clone)]
+pub(crate) struct LimitState {
+    limits: Limits,
+    depth: usize,
+    nodes: usize,
+
+    // True if the next identifier or string is the name of a node
+    at_node_start: bool,
+    in_annotation: bool,
+}
+
+impl LimitState {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            nodes: 0,
+            at_node_start: true,
+            in_annotation: false,
+        }
+    }
+
+    /// Check the next token of the document, which starts at byte `offset`
+    pub fn token(&mut self, kind: TokenKind, text: &str, offset: usize) -> Result<(), LimitError> {
+        let limits = &self.limits;
+        let length = text.len();
+
+        match kind {
+            TokenKind::Identifier | TokenKind::String | TokenKind::RawString => {
+                if length > limits.max_string_length {
+                    return Err(LimitError::StringLength {
+                        limit: limits.max_string_length,
+                        offset,
+                    });
+                }
+
+                if self.at_node_start && !self.in_annotation {
+                    self.at_node_start = false;
+                    self.nodes += 1;
+
+                    if self.nodes > limits.max_nodes {
+                        return Err(LimitError::NodeCount {
+                            limit: limits.max_nodes,
+                            offset,
+                        });
+                    }
+                }
+            }
+            TokenKind::Number => {
+                self.at_node_start = false;
+
+                if length > limits.max_number_length {
+                    return Err(LimitError::NumberLength {
+                        limit: limits.max_number_length,
+                        offset,
+                    });
+                }
+            }
+            TokenKind::BlockComment => {
+                if comment_depth(text) > limits.max_comment_depth {
+                    return Err(LimitError::CommentDepth {
+                        limit: limits.max_comment_depth,
+                        offset,
+                    });
+                }
+            }
+            TokenKind::OpenBrace => {
+                self.at_node_start = true;
+                self.depth += 1;
+
+                if self.depth > limits.max_depth {
+                    return Err(LimitError::Depth {
+                        limit: limits.max_depth,
+                        offset,
+                    });
+                }
+            }
+            TokenKind::CloseBrace => {
+                self.at_node_start = true;
+                self.depth = self.depth.saturating_sub(1);
+            }
+            TokenKind::Newline | TokenKind::Semicolon => self.at_node_start = true,
+            TokenKind::OpenParen => self.in_annotation = true,
+            TokenKind::CloseParen => self.in_annotation = false,
+            TokenKind::Keyword | TokenKind::Equals => self.at_node_start = false,
+
+            // Other error tokens only cover the bad lexeme, so the scan
+            // resumes right after them. Unterminated comments and strings
+            // run to the end of the input (the parser can't get past them
+            // either), so they're still subject to limits.
+            TokenKind::Error if text.starts_with("/*") => {
+                if comment_depth(text) > limits.max_comment_depth {
+                    return Err(LimitError::CommentDepth {
+                        limit: limits.max_comment_depth,
+                        offset,
+                    });
+                }
+            }
+            TokenKind::Error => {
+                if length > limits.max_string_length {
+                    return Err(LimitError::StringLength {
+                        limit: limits.max_string_length,
+                        offset,
+                    });
+                }
+            }
+
+            TokenKind::LineComment
+            | TokenKind::SlashDash
+            | TokenKind::Escline
+            | TokenKind::Whitespace => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_limits_allow_normal_documents() {
+        let input = r#"
+        (config)server "main" port=8080 {
+            /* a /* nested */ comment */
+            route "/" { handler "index"; }
+        }
+        "#;
+
+        assert_eq!(Limits::default().check(input), Ok(()));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let input = format!("{}{}", "a {".repeat(100_000), "}".repeat(100_000));
+
+        assert_eq!(
+            Limits::default().check(&input),
+            Err(LimitError::Depth {
+                limit: 128,
+                offset: 128 * 3 + 2,
+            })
+        );
+    }
+
+    #[test]
+    fn deep_nesting_after_error_tokens() {
+        // `r#x` is a bare identifier, not an unterminated raw string, so it
+        // mustn't hide the rest of the document.
+        let input = format!("r#x {}", "a {".repeat(5000));
+        assert_eq!(
+            Limits::default().check(&input),
+            Err(LimitError::Depth {
+                limit: 128,
+                offset: 4 + 128 * 3 + 2,
+            })
+        );
+
+        let input = format!("r#x {}", "{ a ".repeat(200_000));
+        assert!(matches!(
+            Limits::default().check(&input),
+            Err(LimitError::Depth { limit: 128, .. })
+        ));
+
+        // Invalid characters are skipped one at a time
+        let input = format!("[ {}", "a {".repeat(5000));
+        assert!(matches!(
+            Limits::default().check(&input),
+            Err(LimitError::Depth { limit: 128, .. })
+        ));
+    }
+
+    #[test]
+    fn deep_comments() {
+        let input = format!("a {}{}", "/*".repeat(100_000), "*/".repeat(100_000));
+
+        assert_eq!(
+            Limits::default().check(&input),
+            Err(LimitError::CommentDepth {
+                limit: 128,
+                offset: 2,
+            })
+        );
+
+        // Unterminated comments are also checked
+        assert_eq!(
+            Limits::default().check(&input[..100_000]),
+            Err(LimitError::CommentDepth {
+                limit: 128,
+                offset: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn node_count() {
+        let limits = Limits {
+            max_nodes: 3,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            limits.check("a; b { c; d; }"),
+            Err(LimitError::NodeCount {
+                limit: 3,
+                offset: 10
+            })
+        );
+        assert_eq!(
+            limits.check("a\nb\nc\nd"),
+            Err(LimitError::NodeCount {
+                limit: 3,
+                offset: 6
+            })
+        );
+
+        // Annotations, property keys, and values aren't nodes
+        assert_eq!(limits.check("(t)a key=\"b\" \"c\"\n(u)b { c; }"), Ok(()));
+    }
+
+    #[test]
+    fn node_checker() {
+        let mut checker = NodeChecker::new(Limits {
+            max_depth: 1,
+            ..Limits::default()
+        });
+
+        // Errors after the first node are ignored
+        assert_eq!(checker.check_node("\n// c\na { b; }\nc {{"), Ok(()));
+        assert_eq!(checker.check_node("\nc\nd { e { f; }; }"), Ok(()));
+        assert_eq!(
+            checker.check_node("\nd { e { f; }; }"),
+            Err(LimitError::Depth {
+                limit: 1,
+                offset: 7
+            })
+        );
+
+        // The node count carries over
+        let mut checker = NodeChecker::new(Limits {
+            max_nodes: 2,
+            ..Limits::default()
+        });
+
+        assert_eq!(checker.check_node("a; b"), Ok(()));
+        assert_eq!(checker.check_node(" b; c"), Ok(()));
+        assert_eq!(
+            checker.check_node(" c"),
+            Err(LimitError::NodeCount {
+                limit: 2,
+                offset: 1
+            })
+        );
+    }
+
+    #[test]
+    fn lengths() {
+        let limits = Limits {
+            max_string_length: 5,
+            max_number_length: 3,
+            ..Limits::default()
+        };
+
+        assert_eq!(limits.check("abc \"abc\" 123"), Ok(()));
+        assert_eq!(
+            limits.check("abcdef"),
+            Err(LimitError::StringLength {
+                limit: 5,
+                offset: 0
+            })
+        );
+        assert_eq!(
+            limits.check("a r#\"abc\"#"),
+            Err(LimitError::StringLength {
+                limit: 5,
+                offset: 2
+            })
+        );
+        assert_eq!(
+            limits.check("a \"unterminated"),
+            Err(LimitError::StringLength {
+                limit: 5,
+                offset: 2
+            })
+        );
+        assert_eq!(
+            limits.check("a 1234"),
+            Err(LimitError::NumberLength {
+                limit: 3,
+                offset: 2
+            })
+        );
+    }
+}
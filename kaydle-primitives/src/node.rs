@@ -3,7 +3,9 @@ A system for parsing entire nodes and node lists. Unlike the rest of
 `kaydle-primitives`, this module doesn't expose ordinary nom parsers and
 container types. Instead, because it's designed specifically to support the
 implementation of a serde deserializer, it exposes a set of "processors", which
-can be used to fetch nodes or the contents of nodes step-by-step.
+can be used to fetch nodes or the contents of nodes step-by-step. If you
+don't need the precise control of processors, the [`events`][crate::events]
+module provides a simpler, flat pull parser.
 
 This module makes extensive use of the builder traits defined in
 `kaydle-primitives` (such as [`ValueBuilder`] and [`StringBuilder`]) to allow
@@ -40,7 +42,10 @@ use crate::{
 /// Run a parser on a mutable reference to some input. If the parse is
 /// successful, the input is updated in-place, and the result of the parse
 /// is returned.
-fn run_parser_on<I, O, E>(input: &mut I, mut parser: impl Parser<I, O, E>) -> Result<O, NomErr<E>>
+pub(crate) fn run_parser_on<I, O, E>(
+    input: &mut I,
+    mut parser: impl Parser<I, O, E>,
+) -> Result<O, NomErr<E>>
 where
     I: Clone,
 {
@@ -52,7 +57,7 @@ where
 
 /// Parse the annotation & identifier at the start of a node, or some other
 /// subparser indicating the end of a node list (either a } or an eof)
-fn parse_node_start<'i, T, A, E>(
+pub(crate) fn parse_node_start<'i, T, A, E>(
     end_of_nodes: impl Parser<&'i str, (), E>,
 ) -> impl Parser<&'i str, Option<GenericAnnotated<A, T>>, E>
 where
@@ -120,6 +125,18 @@ pub trait NodeList<'i>: Sized {
         E: FromExternalError<&'i str, CharTryFromError>,
         E: ContextError<&'i str, &'static str>;
 
+    /// Get an independent copy of the rest of this node list, which can be
+    /// used to look ahead at the upcoming nodes without affecting this list.
+    /// The returned [`Siblings`] yields the same nodes that this list would
+    /// yield, and ends at the end of this list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this list is in the middle of a node (that is, if the
+    /// content of a node returned by [`next_node`][Self::next_node] hasn't
+    /// been fully consumed).
+    fn siblings(&self) -> Siblings<'i>;
+
     /// Drain all remaining content from this nodelist. The nodelist is parsed,
     /// and errors are returned, but the nodes are otherwise discarded.
     ///
@@ -169,6 +186,21 @@ impl<'i, T: NodeList<'i>> NodeList<'i> for &mut T {
     {
         T::next_node(*self)
     }
+
+    fn siblings(&self) -> Siblings<'i> {
+        T::siblings(*self)
+    }
+}
+
+/// Where a node or list of nodes is in its document. Used to report the
+/// depth and offset of [`Children`].
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    /// The length of the input originally given to the [`Document`]
+    input_length: usize,
+
+    /// The nesting depth of the nodes. Top-level nodes are at depth 0.
+    depth: usize,
 }
 
 /// Container for a top level kdl document. Returns the nodes in the document.
@@ -177,6 +209,9 @@ pub struct Document<'i> {
     /// The currently unparsed input string, as a suffix of the original input.
     state: &'i str,
 
+    /// The length of the original input
+    input_length: usize,
+
     /// Bool that ensures that node processors fully consume their nodes, so
     /// that parse state remains consistent. Set to true when a node processor
     /// is returned, and only resets to false when that processor is finished.
@@ -188,15 +223,51 @@ impl<'i> Document<'i> {
     pub fn new(input: &'i str) -> Self {
         Self {
             state: input,
+            input_length: input.len(),
             child_in_progress: false,
         }
     }
 
+    /// The part of the input that hasn't been parsed yet.
+    pub fn remaining(&self) -> &'i str {
+        self.state
+    }
+
+    fn location(&self) -> Location {
+        Location {
+            input_length: self.input_length,
+            depth: 0,
+        }
+    }
+
     fn run_parser<T, E>(&mut self, parser: impl Parser<&'i str, T, E>) -> Result<T, NomErr<E>> {
         run_parser_on(&mut self.state, parser)
     }
 }
 
+/// The thing that terminates a list of nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListEnd {
+    /// The list is a [`Document`], terminated by the end of the input
+    Eof,
+
+    /// The list is a set of [`Children`], terminated by a `}`
+    Brace,
+}
+
+impl ListEnd {
+    pub(crate) fn parser<'i, E>(self) -> impl Parser<&'i str, (), E>
+    where
+        E: ParseError<&'i str>,
+        E: ContextError<&'i str, &'static str>,
+    {
+        move |input: &'i str| match self {
+            ListEnd::Eof => eof.value(()).parse(input),
+            ListEnd::Brace => char('}').value(()).context("end of children").parse(input),
+        }
+    }
+}
+
 impl<'i> NodeList<'i> for Document<'i> {
     fn next_node<'s, Annotation, Name, E>(
         &'s mut self,
@@ -215,7 +286,9 @@ impl<'i> NodeList<'i> for Document<'i> {
             )
         }
 
-        self.run_parser(parse_node_start(eof.value(())))
+        let location = self.location();
+
+        self.run_parser(parse_node_start(ListEnd::Eof.parser()))
             .map(move |opt_name| {
                 opt_name.map(move |annotated_name| {
                     self.child_in_progress = true;
@@ -225,14 +298,29 @@ impl<'i> NodeList<'i> for Document<'i> {
                         content: NodeContent {
                             state: &mut self.state,
                             in_progress: &mut self.child_in_progress,
+                            location,
                         },
                     })
                 })
             })
     }
+
+    fn siblings(&self) -> Siblings<'i> {
+        if self.child_in_progress {
+            panic!("Called siblings in the middle of a node. This is a kaydle bug.")
+        }
+
+        Siblings {
+            state: self.state,
+            end: ListEnd::Eof,
+            location: self.location(),
+            child_in_progress: false,
+            done: false,
+        }
+    }
 }
 
-enum InternalNodeEvent<
+pub(crate) enum InternalNodeEvent<
     ArgumentAnnotation,
     Argument,
     PropertyKey,
@@ -286,7 +374,7 @@ pub enum NodeEvent<
 /// thing was in the node, but not the actual value / content.
 pub type RecognizedNodeEvent<'i, 'p> = NodeEvent<'i, 'p, (), (), (), (), ()>;
 
-fn parse_node_event<'i, E, VA, V, K, PA, P>(
+pub(crate) fn parse_node_event<'i, E, VA, V, K, PA, P>(
     input: &'i str,
 ) -> IResult<&'i str, InternalNodeEvent<VA, V, K, PA, P>, E>
 where
@@ -338,6 +426,9 @@ pub struct NodeContent<'i, 'p> {
     /// Bool owned by the parent's list processor. Must be set to false only when
     /// this node has been fully consumed.
     in_progress: &'p mut bool,
+
+    /// Where this node is in the document
+    location: Location,
 }
 
 impl<'i, 'p> NodeContent<'i, 'p> {
@@ -398,6 +489,12 @@ impl<'i, 'p> NodeContent<'i, 'p> {
                 },
                 InternalNodeEvent::Children => NodeEvent::Children {
                     children: Children {
+                        // The `{` was the last character parsed
+                        offset: self.location.input_length - self.state.len() - 1,
+                        location: Location {
+                            depth: self.location.depth + 1,
+                            ..self.location
+                        },
                         state: self.state,
                         in_progress: self.in_progress,
                         child_in_progress: false,
@@ -422,24 +519,46 @@ impl<'i, 'p> NodeContent<'i, 'p> {
         E: FromExternalError<&'i str, BoundsError>,
         E: ContextError<&'i str, &'static str>,
     {
-        self = match self.next_event()? {
-            RecognizedNodeEvent::Argument { tail, .. } => tail,
-            RecognizedNodeEvent::Property { tail, .. } => tail,
-            RecognizedNodeEvent::Children { children } => return children.drain(),
-            RecognizedNodeEvent::End => return Ok(DrainOutcome::Empty),
-        };
+        // Nested children are drained in a loop, rather than recursively, so
+        // that deeply nested documents can't overflow the stack. `depth` is
+        // the number of children blocks that are currently open, and
+        // `in_list` is true between the nodes of the innermost one.
+        let mut depth = 0;
+        let mut in_list = false;
+        let mut outcome = DrainOutcome::Empty;
 
         loop {
-            self = match self.next_event()? {
-                RecognizedNodeEvent::Argument { tail, .. } => tail,
-                RecognizedNodeEvent::Property { tail, .. } => tail,
-                RecognizedNodeEvent::Children { children } => {
-                    children.drain()?;
-                    return Ok(DrainOutcome::NotEmpty);
+            if in_list {
+                match self.run_parser(parse_node_start::<(), (), E>(ListEnd::Brace.parser()))? {
+                    Some(_) => {
+                        outcome = DrainOutcome::NotEmpty;
+                        in_list = false;
+                    }
+                    None => {
+                        depth -= 1;
+
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                }
+            } else {
+                match self.run_parser(parse_node_event::<E, (), (), (), (), ()>)? {
+                    InternalNodeEvent::Argument(_) | InternalNodeEvent::Property(_) => {
+                        outcome = DrainOutcome::NotEmpty
+                    }
+                    InternalNodeEvent::Children => {
+                        depth += 1;
+                        in_list = true;
+                    }
+                    InternalNodeEvent::End if depth == 0 => break,
+                    InternalNodeEvent::End => in_list = true,
                 }
-                RecognizedNodeEvent::End => return Ok(DrainOutcome::NotEmpty),
             }
         }
+
+        *self.in_progress = false;
+        Ok(outcome)
     }
 
     fn run_parser<T, E>(&mut self, parser: impl Parser<&'i str, T, E>) -> Result<T, NomErr<E>> {
@@ -462,9 +581,28 @@ pub struct Children<'i, 'p> {
     /// that parse state remains consistent. Set to false when a node processor
     /// is returns, and only resets to true when that processor is finished.
     child_in_progress: bool,
+
+    /// Where the child nodes are in the document
+    location: Location,
+
+    /// The byte offset of the `{` that opened these children
+    offset: usize,
 }
 
 impl<'i> Children<'i, '_> {
+    /// How deeply these children are nested. The children of a top-level
+    /// node are at depth 1, the same way
+    /// [`Limits::max_depth`][crate::limits::Limits::max_depth] counts them.
+    pub fn depth(&self) -> usize {
+        self.location.depth
+    }
+
+    /// The byte offset of the `{` that opened these children, from the start
+    /// of the input given to the [`Document`].
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     fn run_parser<T, E>(&mut self, parser: impl Parser<&'i str, T, E>) -> Result<T, NomErr<E>> {
         run_parser_on(self.state, parser)
     }
@@ -497,7 +635,7 @@ impl<'i> NodeList<'i> for Children<'i, '_> {
             )
         }
 
-        self.run_parser(parse_node_start(char('}').value(())))
+        self.run_parser(parse_node_start(ListEnd::Brace.parser()))
             .map(|opt_name| match opt_name {
                 // None here means that we successfully parsed the end-of-children. Inform the parent.
                 None => {
@@ -512,11 +650,109 @@ impl<'i> NodeList<'i> for Children<'i, '_> {
                         content: NodeContent {
                             state: self.state,
                             in_progress: &mut self.child_in_progress,
+                            location: self.location,
+                        },
+                    }))
+                }
+            })
+    }
+
+    fn siblings(&self) -> Siblings<'i> {
+        if self.child_in_progress {
+            panic!("Called siblings in the middle of a node. This is a kaydle bug.")
+        }
+
+        Siblings {
+            state: self.state,
+            end: ListEnd::Brace,
+            location: self.location,
+            child_in_progress: false,
+            done: !*self.in_progress,
+        }
+    }
+}
+
+/// An independent copy of the remaining nodes of some other [`NodeList`],
+/// created by [`NodeList::siblings`]. Because it's a copy, it can be used to
+/// look ahead in a node list without disturbing the original list. It's cheap
+/// to create and to clone.
+#[derive(Debug, Clone)]
+pub struct Siblings<'i> {
+    /// The currently unparsed input string, as a suffix of the original input.
+    state: &'i str,
+
+    /// The thing that terminates this list of nodes
+    end: ListEnd,
+
+    /// Where the nodes are in the document
+    location: Location,
+
+    /// Bool that ensures that node processors fully consume their nodes, so
+    /// that parse state remains consistent. Set to true when a node processor
+    /// is returned, and only resets to false when that processor is finished.
+    child_in_progress: bool,
+
+    /// Set to true after the end of the list has been parsed, to ensure that
+    /// `next_node` exhibits fused behavior.
+    done: bool,
+}
+
+impl<'i> Siblings<'i> {
+    fn run_parser<T, E>(&mut self, parser: impl Parser<&'i str, T, E>) -> Result<T, NomErr<E>> {
+        run_parser_on(&mut self.state, parser)
+    }
+}
+
+impl<'i> NodeList<'i> for Siblings<'i> {
+    fn next_node<'s, A, N, E>(
+        &'s mut self,
+    ) -> Result<Option<GenericAnnotated<A, Node<'i, 's, N>>>, nom::Err<E>>
+    where
+        N: StringBuilder<'i>,
+        A: AnnotationBuilder<'i>,
+        E: ParseError<&'i str>,
+        E: TagError<&'i str, &'static str>,
+        E: FromExternalError<&'i str, CharTryFromError>,
+        E: ContextError<&'i str, &'static str>,
+    {
+        if self.done {
+            return Ok(None);
+        }
+
+        if self.child_in_progress {
+            panic!(
+                "Called next_node before the previous node was fully parsed. This is a kaydle bug."
+            )
+        }
+
+        self.run_parser(parse_node_start(self.end.parser()))
+            .map(|opt_name| match opt_name {
+                None => {
+                    self.done = true;
+                    None
+                }
+                Some(annotated_name) => {
+                    self.child_in_progress = true;
+
+                    Some(annotated_name.map_item(|name| Node {
+                        name,
+                        content: NodeContent {
+                            state: &mut self.state,
+                            in_progress: &mut self.child_in_progress,
+                            location: self.location,
                         },
                     }))
                 }
             })
     }
+
+    fn siblings(&self) -> Siblings<'i> {
+        if self.child_in_progress {
+            panic!("Called siblings in the middle of a node. This is a kaydle bug.")
+        }
+
+        self.clone()
+    }
 }
 
 /// This test may not look like much, but all the relevant components are
@@ -544,3 +780,109 @@ fn test_full_document_drain() {
     let res: Result<DrainOutcome, nom::Err<()>> = processor.drain();
     assert_eq!(res.expect("parse error"), DrainOutcome::NotEmpty);
 }
+
+#[test]
+fn test_siblings_lookahead() {
+    use crate::string::KdlString;
+
+    let content = r#"
+    parent {
+        a 1
+        b { c 2; }
+        a 3
+    }
+    next
+    "#;
+
+    let mut document = Document::new(content);
+
+    let node: RecognizedAnnotation<Node<'_, '_, KdlString<'_>>> = document
+        .next_node::<(), _, ()>()
+        .expect("parse error")
+        .expect("no node");
+    assert_eq!(node.item.name, "parent");
+
+    let mut children = match node.item.content.next_event::<(), (), (), (), (), ()>() {
+        Ok(RecognizedNodeEvent::Children { children }) => children,
+        _ => panic!("expected children"),
+    };
+
+    // Look ahead through the children, collecting their names
+    let mut siblings = children.siblings();
+    let mut names = Vec::new();
+    while let Some(node) = siblings
+        .next_node::<(), KdlString<'_>, ()>()
+        .expect("parse error")
+    {
+        names.push(node.item.name.into_string());
+        node.item.content.drain::<()>().expect("parse error");
+    }
+    assert_eq!(names, ["a", "b", "a"]);
+
+    // The lookahead ends at the end of the children, and is fused
+    assert!(siblings
+        .next_node::<(), (), ()>()
+        .expect("parse error")
+        .is_none());
+
+    // The original children are unaffected
+    let node = children
+        .next_node::<(), KdlString<'_>, ()>()
+        .expect("parse error")
+        .expect("no node");
+    assert_eq!(node.item.name, "a");
+    node.item.content.drain::<()>().expect("parse error");
+
+    let outcome: Result<DrainOutcome, nom::Err<()>> = children.drain();
+    assert_eq!(outcome.expect("parse error"), DrainOutcome::NotEmpty);
+
+    let node = document
+        .next_node::<(), KdlString<'_>, ()>()
+        .expect("parse error")
+        .expect("no node");
+    assert_eq!(node.item.name, "next");
+}
+
+#[test]
+fn test_deep_drain() {
+    // Draining doesn't recurse, so this doesn't overflow the stack
+    let content = format!("{}{}", "a {".repeat(100_000), "}".repeat(100_000));
+
+    let res: Result<DrainOutcome, nom::Err<()>> = Document::new(&content).drain();
+    assert_eq!(res.expect("parse error"), DrainOutcome::NotEmpty);
+}
+
+#[test]
+fn test_children_depth() {
+    let content = "a { b { c; } }";
+
+    let mut document = Document::new(content);
+
+    let node: RecognizedAnnotation<RecognizedNode<'_, '_>> = document
+        .next_node::<(), (), ()>()
+        .expect("parse error")
+        .expect("no node");
+
+    let mut children = match node.item.content.next_event::<(), (), (), (), (), ()>() {
+        Ok(RecognizedNodeEvent::Children { children }) => children,
+        _ => panic!("expected children"),
+    };
+    assert_eq!((children.depth(), children.offset()), (1, 2));
+
+    let node = children
+        .next_node::<(), (), ()>()
+        .expect("parse error")
+        .expect("no node");
+
+    let grandchildren = match node.item.content.next_event::<(), (), (), (), (), ()>() {
+        Ok(RecognizedNodeEvent::Children { children }) => children,
+        _ => panic!("expected children"),
+    };
+    assert_eq!((grandchildren.depth(), grandchildren.offset()), (2, 6));
+
+    let outcome: Result<DrainOutcome, nom::Err<()>> = grandchildren.drain();
+    assert_eq!(outcome.expect("parse error"), DrainOutcome::NotEmpty);
+
+    let outcome: Result<DrainOutcome, nom::Err<()>> = children.drain();
+    assert_eq!(outcome.expect("parse error"), DrainOutcome::Empty);
+}
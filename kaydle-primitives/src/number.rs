@@ -9,20 +9,22 @@ Currently, we use a simple set of rules to decide if a KDL number should be
 parsed as an [`i64`], [`u64`], or [`f64`]:
 
 - If the number contains a fractional part or exponent, it's unconditionally
-parsed as an `f64`.
+  parsed as an `f64`.
 - Otherwise, if it's negative, it's parsed as an `i64`.
 - Otherwise, it's parsed as a `u64`.
 
 These rules may change in the future. Possible improvements:
 
 - If it includes an exponent, it might be an integer, even if it includes a
-fractional parts.
+  fractional parts.
 - If it's an integer but it overflows an `i64` or `u64`, it could be stored in
-an `f64` instead.
+  an `f64` instead.
 - It's also possible we could use type hints (from serde or KDL annotations) to
-guide the parse as well.
+  guide the parse as well.
 */
 
+use std::fmt;
+
 use arrayvec::ArrayString;
 use memchr::memchr3;
 use nom::{
@@ -62,28 +64,43 @@ pub enum KdlInt {
     Unsigned(u64),
 }
 
-/// A Bounds error occurred during number parsing. This type is incomplete and
-/// will grow in the future.
-pub struct BoundsError;
+/// A Bounds error occurred during number parsing: the number didn't fit in
+/// the type it was being parsed into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundsError {
+    /// The name of the type the number didn't fit into, like `"u64"`
+    pub target: &'static str,
+}
+
+impl BoundsError {
+    /// Create a new `BoundsError` for the named type
+    pub const fn new(target: &'static str) -> Self {
+        Self { target }
+    }
+}
+
+impl fmt::Display for BoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "number out of range for {}", self.target)
+    }
+}
+
+impl std::error::Error for BoundsError {}
 
 impl IntBuilder for KdlInt {
     #[inline]
     fn add_digit(self, digit: u32, radix: u32) -> Result<Self, BoundsError> {
         match self {
-            KdlInt::Signed(value) => Ok(KdlInt::Signed(
-                value
-                    .checked_mul(radix as i64)
-                    .ok_or(BoundsError)?
-                    .checked_sub(digit as i64)
-                    .ok_or(BoundsError)?,
-            )),
-            KdlInt::Unsigned(value) => Ok(KdlInt::Unsigned(
-                value
-                    .checked_mul(radix as u64)
-                    .ok_or(BoundsError)?
-                    .checked_add(digit as u64)
-                    .ok_or(BoundsError)?,
-            )),
+            KdlInt::Signed(value) => value
+                .checked_mul(radix as i64)
+                .and_then(|value| value.checked_sub(digit as i64))
+                .map(KdlInt::Signed)
+                .ok_or(BoundsError::new("i64")),
+            KdlInt::Unsigned(value) => value
+                .checked_mul(radix as u64)
+                .and_then(|value| value.checked_add(digit as u64))
+                .map(KdlInt::Unsigned)
+                .ok_or(BoundsError::new("u64")),
         }
     }
 
@@ -291,6 +308,14 @@ impl NumberBuilder for KdlNumber {
     type IntForm = KdlInt;
 
     fn from_str(input: &str) -> Result<Self, BoundsError> {
+        let target = if memchr3(b'.', b'e', b'E', input.as_bytes()).is_some() {
+            "f64"
+        } else if input.starts_with('-') {
+            "i64"
+        } else {
+            "u64"
+        };
+
         let mut buffer: ArrayString<64>;
 
         let input = if input.contains('_') {
@@ -299,26 +324,20 @@ impl NumberBuilder for KdlNumber {
             input
                 .split('_')
                 .try_for_each(|s| buffer.try_push_str(s).ok())
-                .ok_or(BoundsError)?;
+                .ok_or(BoundsError::new(target))?;
 
             &buffer
         } else {
             input
         };
 
-        if memchr3(b'.', b'e', b'E', input.as_bytes()).is_some() {
-            input.parse().map(KdlNumber::Float).map_err(|_| BoundsError)
-        } else if input.starts_with('-') {
-            input
-                .parse()
-                .map(KdlNumber::Signed)
-                .map_err(|_| BoundsError)
-        } else {
-            input
-                .parse()
-                .map(KdlNumber::Unsigned)
-                .map_err(|_| BoundsError)
-        }
+        let number = match target {
+            "f64" => input.parse().ok().map(KdlNumber::Float),
+            "i64" => input.parse().ok().map(KdlNumber::Signed),
+            _ => input.parse().ok().map(KdlNumber::Unsigned),
+        };
+
+        number.ok_or(BoundsError::new(target))
     }
 
     fn from_int(input: KdlInt) -> Self {
@@ -329,7 +348,7 @@ impl NumberBuilder for KdlNumber {
 /// A KDL Number. The KDL spec doesn't distinguish between integers and floats,
 /// or between signed an unsigned numbers, but kaydle uses a heuristic to pick
 /// a type for deserialization purposes.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum KdlNumber {
     /// A signed integer; returned by the parser if the number was negative and
     /// had no fractional component or exponent.
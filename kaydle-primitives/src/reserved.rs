@@ -0,0 +1,628 @@
+/*!
+Validation of, and conversions for, the type annotations reserved by the KDL
+spec, like `(u8)`, `(ipv4)`, or `(uuid)`.
+
+KDL itself treats annotations as opaque strings, but the spec reserves a set
+of them for specific types. [`validate`] checks that a value is compatible
+with its (reserved) annotation:
+
+```
+use kaydle_primitives::{number::KdlNumber, reserved::validate, string::KdlString, value::KdlValue};
+
+assert!(validate("u8", &KdlValue::Number(KdlNumber::Unsigned(255))).is_ok());
+assert!(validate("u8", &KdlValue::Number(KdlNumber::Unsigned(300))).is_err());
+
+let address = KdlValue::String(KdlString::from_borrowed("1.2.3.4"));
+assert!(validate("ipv4", &address).is_ok());
+assert!(validate("ipv6", &address).is_err());
+
+// Annotations that aren't reserved are always valid
+assert!(validate("celsius", &address).is_ok());
+```
+
+Most formats are checked directly. The formats that need a full parser
+(`url`, `irl`, `regex`, `date-time`, `date`, and `time`) are only checked for
+being strings, unless the corresponding `url`, `regex`, or `chrono` cargo
+feature is enabled.
+
+Types that have a reserved annotation implement [`FromReserved`], which
+converts a KDL value to that type. The `uuid`, `url`, `regex`, and `chrono`
+cargo features add implementations for types from those crates.
+*/
+
+use std::{
+    error::Error,
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use serde::{de, Deserialize};
+
+use crate::{number::KdlNumber, value::KdlValue};
+
+/// The annotations reserved by the KDL spec for numbers
+const NUMBER_ANNOTATIONS: &[&str] = &[
+    "i8",
+    "i16",
+    "i32",
+    "i64",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "isize",
+    "usize",
+    "f32",
+    "f64",
+    "decimal64",
+    "decimal128",
+];
+
+/// The annotations reserved by the KDL spec for strings
+const STRING_ANNOTATIONS: &[&str] = &[
+    "date-time",
+    "time",
+    "date",
+    "duration",
+    "decimal",
+    "currency",
+    "country-2",
+    "country-3",
+    "country-subdivision",
+    "email",
+    "idn-email",
+    "hostname",
+    "idn-hostname",
+    "ipv4",
+    "ipv6",
+    "url",
+    "url-reference",
+    "irl",
+    "irl-reference",
+    "url-template",
+    "uuid",
+    "regex",
+    "base64",
+];
+
+/// Get the static version of a reserved annotation, or `None` if the
+/// annotation isn't reserved.
+fn reserved_name(annotation: &str) -> Option<&'static str> {
+    NUMBER_ANNOTATIONS
+        .iter()
+        .chain(STRING_ANNOTATIONS)
+        .copied()
+        .find(|&name| name == annotation)
+}
+
+/// Returns true if the annotation is one of the type annotations reserved
+/// by the KDL spec.
+pub fn is_reserved(annotation: &str) -> bool {
+    reserved_name(annotation).is_some()
+}
+
+/// A value wasn't compatible with its reserved type annotation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReservedError {
+    /// The annotation, like `"u8"`
+    pub annotation: &'static str,
+
+    /// Why the value didn't match
+    pub reason: String,
+}
+
+impl ReservedError {
+    fn new(annotation: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            annotation,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ReservedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "value doesn't match its ({}) annotation: {}",
+            self.annotation, self.reason
+        )
+    }
+}
+
+impl Error for ReservedError {}
+
+/// Check that a value is compatible with its annotation. Values with
+/// annotations that aren't reserved by the KDL spec are always valid.
+pub fn validate(annotation: &str, value: &KdlValue<'_>) -> Result<(), ReservedError> {
+    let annotation = match reserved_name(annotation) {
+        None => return Ok(()),
+        Some(annotation) => annotation,
+    };
+
+    let error = |reason: &str| Err(ReservedError::new(annotation, reason));
+
+    if NUMBER_ANNOTATIONS.contains(&annotation) {
+        let number = match *value {
+            KdlValue::Number(number) => number,
+            _ => return error("expected a number"),
+        };
+
+        return match annotation {
+            "i8" => check_int(annotation, number, i8::MIN.into(), i8::MAX.into()),
+            "i16" => check_int(annotation, number, i16::MIN.into(), i16::MAX.into()),
+            "i32" => check_int(annotation, number, i32::MIN.into(), i32::MAX.into()),
+            "i64" => check_int(annotation, number, i64::MIN.into(), i64::MAX.into()),
+            "u8" => check_int(annotation, number, 0, u8::MAX.into()),
+            "u16" => check_int(annotation, number, 0, u16::MAX.into()),
+            "u32" => check_int(annotation, number, 0, u32::MAX.into()),
+            "u64" => check_int(annotation, number, 0, u64::MAX.into()),
+            "isize" => check_int(annotation, number, isize::MIN as i128, isize::MAX as i128),
+            "usize" => check_int(annotation, number, 0, usize::MAX as i128),
+            "f32" => match number {
+                KdlNumber::Float(value) if value.is_finite() && value.abs() > f32::MAX as f64 => {
+                    error("out of range for f32")
+                }
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        };
+    }
+
+    let s = match *value {
+        KdlValue::String(ref s) => s.as_str(),
+        _ => return error("expected a string"),
+    };
+
+    match annotation {
+        "ipv4" => s
+            .parse::<Ipv4Addr>()
+            .map(drop)
+            .or_else(|_| error("not an IPv4 address")),
+        "ipv6" => s
+            .parse::<Ipv6Addr>()
+            .map(drop)
+            .or_else(|_| error("not an IPv6 address")),
+        "uuid" => match is_uuid(s) {
+            true => Ok(()),
+            false => error("not a UUID"),
+        },
+        "base64" => match decode_base64(s) {
+            Ok(_) => Ok(()),
+            Err(reason) => error(reason),
+        },
+        "decimal" => match s.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(()),
+            _ => error("not a decimal number"),
+        },
+        "currency" | "country-3" => match is_upper_alpha(s, 3) {
+            true => Ok(()),
+            false => error("expected 3 uppercase letters"),
+        },
+        "country-2" => match is_upper_alpha(s, 2) {
+            true => Ok(()),
+            false => error("expected 2 uppercase letters"),
+        },
+        "country-subdivision" => match s.split_once('-') {
+            Some((country, subdivision))
+                if is_upper_alpha(country, 2)
+                    && (1..=3).contains(&subdivision.len())
+                    && subdivision.bytes().all(|b| b.is_ascii_alphanumeric()) =>
+            {
+                Ok(())
+            }
+            _ => error("expected a country code and subdivision, like `US-CA`"),
+        },
+        "email" | "idn-email" => match s.split_once('@') {
+            Some((local, domain))
+                if !local.is_empty() && !domain.is_empty() && !domain.contains('@') =>
+            {
+                Ok(())
+            }
+            _ => error("not an email address"),
+        },
+        "hostname" => match is_hostname(s, |c| c.is_ascii_alphanumeric()) {
+            true => Ok(()),
+            false => error("not a hostname"),
+        },
+        "idn-hostname" => match is_hostname(s, char::is_alphanumeric) {
+            true => Ok(()),
+            false => error("not a hostname"),
+        },
+        #[cfg(feature = "url")]
+        "url" | "irl" => url::Url::from_reserved(value).map(drop),
+        #[cfg(feature = "regex")]
+        "regex" => regex::Regex::from_reserved(value).map(drop),
+        #[cfg(feature = "chrono")]
+        "date-time" => chrono::DateTime::<chrono::FixedOffset>::from_reserved(value).map(drop),
+        #[cfg(feature = "chrono")]
+        "date" => chrono::NaiveDate::from_reserved(value).map(drop),
+        #[cfg(feature = "chrono")]
+        "time" => chrono::NaiveTime::from_reserved(value).map(drop),
+        _ => Ok(()),
+    }
+}
+
+fn check_int(
+    annotation: &'static str,
+    number: KdlNumber,
+    min: i128,
+    max: i128,
+) -> Result<(), ReservedError> {
+    let value: i128 = match number {
+        KdlNumber::Signed(value) => value.into(),
+        KdlNumber::Unsigned(value) => value.into(),
+        KdlNumber::Float(_) => {
+            return Err(ReservedError::new(annotation, "expected an integer"));
+        }
+    };
+
+    match (min..=max).contains(&value) {
+        true => Ok(()),
+        false => Err(ReservedError::new(
+            annotation,
+            format!("{value} is out of range for {annotation}"),
+        )),
+    }
+}
+
+fn is_upper_alpha(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+/// Check for a UUID in its usual hyphenated form, like
+/// `f81d4fae-7dec-11d0-a765-00a0c91e6bf6`
+fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Decode standard base64, as used by the `(base64)` annotation. Whitespace
+/// is ignored (so that long strings can be split over several lines), and
+/// padding is optional. [`validate`] accepts exactly the strings that this
+/// can decode.
+pub fn decode_base64(s: &str) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = Vec::with_capacity(s.len() / 4 * 3);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    let mut padding = 0;
+
+    for b in s.bytes().filter(|b| !b.is_ascii_whitespace()) {
+        let sextet = match b {
+            b'=' => {
+                padding += 1;
+                continue;
+            }
+            _ if padding > 0 => return Err("data after padding"),
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err("invalid character"),
+        };
+
+        buffer = (buffer << 6) | u32::from(sextet);
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    // A single leftover sextet can't encode a whole byte
+    match bits {
+        6 => Err("truncated data"),
+        _ if padding > 2 => Err("too much padding"),
+        _ => Ok(bytes),
+    }
+}
+
+/// Check for an RFC 1123 hostname, with a custom rule for which characters
+/// are allowed in labels (in addition to `-`).
+fn is_hostname(s: &str, is_label_char: impl Fn(char) -> bool) -> bool {
+    let s = s.strip_suffix('.').unwrap_or(s);
+
+    !s.is_empty()
+        && s.len() <= 253
+        && s.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c == '-' || is_label_char(c))
+        })
+}
+
+/// Types that correspond to one of the reserved KDL type annotations, and
+/// can be converted from a KDL value with that annotation.
+pub trait FromReserved: Sized {
+    /// The reserved annotation for this type, like `"ipv4"`
+    const ANNOTATION: &'static str;
+
+    /// Convert a KDL value to this type. The value's annotation isn't
+    /// checked; it's assumed to be [`ANNOTATION`][Self::ANNOTATION], or
+    /// absent.
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError>;
+}
+
+/// Helper for implementing [`FromReserved`] for types that are parsed from
+/// a string
+fn parse_string<T, E: fmt::Display>(
+    annotation: &'static str,
+    value: &KdlValue<'_>,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<T, ReservedError> {
+    match *value {
+        KdlValue::String(ref s) => {
+            parse(s.as_str()).map_err(|err| ReservedError::new(annotation, err.to_string()))
+        }
+        _ => Err(ReservedError::new(annotation, "expected a string")),
+    }
+}
+
+macro_rules! from_reserved_int {
+    ($($int:ident)*) => {$(
+        impl FromReserved for $int {
+            const ANNOTATION: &'static str = stringify!($int);
+
+            fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+                validate(Self::ANNOTATION, value)?;
+
+                match *value {
+                    KdlValue::Number(KdlNumber::Signed(value)) => Ok(value as $int),
+                    KdlValue::Number(KdlNumber::Unsigned(value)) => Ok(value as $int),
+                    _ => unreachable!("validate checks that the value is an integer"),
+                }
+            }
+        }
+    )*};
+}
+
+from_reserved_int! { i8 i16 i32 i64 u8 u16 u32 u64 isize usize }
+
+impl FromReserved for f64 {
+    const ANNOTATION: &'static str = "f64";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        match *value {
+            KdlValue::Number(KdlNumber::Float(value)) => Ok(value),
+            KdlValue::Number(KdlNumber::Signed(value)) => Ok(value as f64),
+            KdlValue::Number(KdlNumber::Unsigned(value)) => Ok(value as f64),
+            _ => Err(ReservedError::new(Self::ANNOTATION, "expected a number")),
+        }
+    }
+}
+
+impl FromReserved for f32 {
+    const ANNOTATION: &'static str = "f32";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        validate(Self::ANNOTATION, value)?;
+        f64::from_reserved(value).map(|value| value as f32)
+    }
+}
+
+impl FromReserved for Ipv4Addr {
+    const ANNOTATION: &'static str = "ipv4";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(Self::ANNOTATION, value, str::parse)
+    }
+}
+
+impl FromReserved for Ipv6Addr {
+    const ANNOTATION: &'static str = "ipv6";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(Self::ANNOTATION, value, str::parse)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl FromReserved for uuid::Uuid {
+    const ANNOTATION: &'static str = "uuid";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(Self::ANNOTATION, value, uuid::Uuid::parse_str)
+    }
+}
+
+#[cfg(feature = "url")]
+impl FromReserved for url::Url {
+    const ANNOTATION: &'static str = "url";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(Self::ANNOTATION, value, url::Url::parse)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl FromReserved for regex::Regex {
+    const ANNOTATION: &'static str = "regex";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(Self::ANNOTATION, value, regex::Regex::new)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromReserved for chrono::DateTime<chrono::FixedOffset> {
+    const ANNOTATION: &'static str = "date-time";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(
+            Self::ANNOTATION,
+            value,
+            chrono::DateTime::parse_from_rfc3339,
+        )
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromReserved for chrono::NaiveDate {
+    const ANNOTATION: &'static str = "date";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(Self::ANNOTATION, value, str::parse)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromReserved for chrono::NaiveTime {
+    const ANNOTATION: &'static str = "time";
+
+    fn from_reserved(value: &KdlValue<'_>) -> Result<Self, ReservedError> {
+        parse_string(Self::ANNOTATION, value, str::parse)
+    }
+}
+
+/// Deserialize a [`FromReserved`] type from a KDL value. This is intended to
+/// be used with `#[serde(deserialize_with)]`, for types that don't implement
+/// [`Deserialize`] themselves:
+///
+/// ```ignore
+/// #[derive(Deserialize)]
+/// struct Route {
+///     #[serde(deserialize_with = "kaydle_primitives::reserved::deserialize")]
+///     pattern: regex::Regex,
+/// }
+/// ```
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: FromReserved,
+{
+    let value = KdlValue::deserialize(deserializer)?;
+    T::from_reserved(&value).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::string::KdlString;
+
+    fn string(s: &str) -> KdlValue<'_> {
+        KdlValue::String(KdlString::from_borrowed(s))
+    }
+
+    #[test]
+    fn integers() {
+        let value = KdlValue::Number(KdlNumber::Unsigned(300));
+        assert!(validate("u16", &value).is_ok());
+        assert_eq!(
+            validate("u8", &value),
+            Err(ReservedError::new("u8", "300 is out of range for u8"))
+        );
+
+        let value = KdlValue::Number(KdlNumber::Signed(-1));
+        assert!(validate("i8", &value).is_ok());
+        assert!(validate("u64", &value).is_err());
+
+        let value = KdlValue::Number(KdlNumber::Float(1.5));
+        assert_eq!(
+            validate("i32", &value),
+            Err(ReservedError::new("i32", "expected an integer"))
+        );
+        assert!(validate("f32", &value).is_ok());
+        assert!(validate("decimal64", &value).is_ok());
+
+        assert_eq!(
+            u8::from_reserved(&KdlValue::Number(KdlNumber::Unsigned(8))),
+            Ok(8)
+        );
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            validate("i32", &string("10")),
+            Err(ReservedError::new("i32", "expected a number"))
+        );
+        assert_eq!(
+            validate("uuid", &KdlValue::Null),
+            Err(ReservedError::new("uuid", "expected a string"))
+        );
+        assert!(validate("url-template", &string("{anything}")).is_ok());
+    }
+
+    #[test]
+    fn strings() {
+        assert!(validate("ipv6", &string("::1")).is_ok());
+        assert!(validate("uuid", &string("f81d4fae-7dec-11d0-a765-00a0c91e6bf6")).is_ok());
+        assert!(validate("uuid", &string("f81d4fae-7dec-11d0-a765")).is_err());
+        assert!(validate("base64", &string("aGVsbG8=")).is_ok());
+        assert!(validate("base64", &string("aGVsbG8")).is_ok());
+        assert!(validate("base64", &string("aGVs\nbG8=")).is_ok());
+        assert!(validate("base64", &string("aGVsbG8=x")).is_err());
+        assert!(validate("base64", &string("aGVsb")).is_err());
+        assert!(validate("country-2", &string("NZ")).is_ok());
+        assert!(validate("country-3", &string("nzl")).is_err());
+        assert!(validate("country-subdivision", &string("US-CA")).is_ok());
+        assert!(validate("currency", &string("EUR")).is_ok());
+        assert!(validate("email", &string("kat@example.com")).is_ok());
+        assert!(validate("email", &string("example.com")).is_err());
+        assert!(validate("hostname", &string("kdl.dev")).is_ok());
+        assert!(validate("hostname", &string("-kdl.dev")).is_err());
+        assert!(validate("idn-hostname", &string("bücher.example")).is_ok());
+        assert!(validate("decimal", &string("1.25e3")).is_ok());
+        assert!(validate("decimal", &string("one")).is_err());
+
+        assert_eq!(
+            Ipv4Addr::from_reserved(&string("1.2.3.4")),
+            Ok(Ipv4Addr::new(1, 2, 3, 4))
+        );
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("aGVsbG8="), Ok(b"hello".to_vec()));
+        assert_eq!(decode_base64("aGVs bG8\n"), Ok(b"hello".to_vec()));
+        assert_eq!(decode_base64("3q2+7w"), Ok(vec![0xDE, 0xAD, 0xBE, 0xEF]));
+        assert_eq!(decode_base64(""), Ok(Vec::new()));
+        assert_eq!(decode_base64("aGVsbG8=x"), Err("data after padding"));
+        assert_eq!(decode_base64("aGVsb"), Err("truncated data"));
+        assert_eq!(decode_base64("aGVsbG8==="), Err("too much padding"));
+        assert_eq!(decode_base64("aGV$"), Err("invalid character"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex() {
+        assert!(validate("regex", &string("^a+$")).is_ok());
+        assert!(validate("regex", &string("(")).is_err());
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn url() {
+        assert!(validate("url", &string("https://kdl.dev/")).is_ok());
+        assert!(validate("url", &string("not a url")).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        assert!(validate("date-time", &string("2022-09-01T12:00:00Z")).is_ok());
+        assert!(validate("date", &string("2022-09-01")).is_ok());
+        assert!(validate("date", &string("2022-13-01")).is_err());
+        assert!(validate("time", &string("12:00:00")).is_ok());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid() {
+        assert_eq!(
+            uuid::Uuid::from_reserved(&string("f81d4fae-7dec-11d0-a765-00a0c91e6bf6"))
+                .unwrap()
+                .to_string(),
+            "f81d4fae-7dec-11d0-a765-00a0c91e6bf6"
+        );
+    }
+}
@@ -9,6 +9,7 @@ use std::{
     fmt::{self, Formatter},
     hash::{Hash, Hasher},
     iter::FromIterator,
+    marker::PhantomData,
     ops::{Deref, DerefMut, Index, RangeFrom, RangeTo},
 };
 
@@ -158,15 +159,15 @@ impl Serialize for KdlString<'_> {
     }
 }
 
-impl<'de> Deserialize<'de> for KdlString<'de> {
+impl<'de: 'a, 'a> Deserialize<'de> for KdlString<'a> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        struct KdlStringVisitor;
+        struct KdlStringVisitor<'a>(PhantomData<KdlString<'a>>);
 
-        impl<'de> de::Visitor<'de> for KdlStringVisitor {
-            type Value = KdlString<'de>;
+        impl<'de: 'a, 'a> de::Visitor<'de> for KdlStringVisitor<'a> {
+            type Value = KdlString<'a>;
 
             fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
                 write!(formatter, "a KDL string")
@@ -194,16 +195,16 @@ impl<'de> Deserialize<'de> for KdlString<'de> {
             }
         }
 
-        deserializer.deserialize_string(KdlStringVisitor)
+        deserializer.deserialize_string(KdlStringVisitor(PhantomData))
     }
 
     fn deserialize_in_place<D>(deserializer: D, place: &mut Self) -> Result<(), D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        struct KdlStringVisitor<'de, 'a>(&'a mut KdlString<'de>);
+        struct KdlStringVisitor<'a, 'p>(&'p mut KdlString<'a>);
 
-        impl<'de> de::Visitor<'de> for KdlStringVisitor<'de, '_> {
+        impl<'de: 'a, 'a> de::Visitor<'de> for KdlStringVisitor<'a, '_> {
             type Value = ();
 
             fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
@@ -333,7 +334,7 @@ where
 }
 
 /// Parse a raw string, resembling `r##"abc"##`
-pub fn parse_raw_string<'i, E: ParseError<&'i str>>(input: &'i str) -> IResult<&'i str, &'i str, E>
+pub fn parse_raw_string<'i, E>(input: &'i str) -> IResult<&'i str, &'i str, E>
 where
     E: ParseError<&'i str>,
 {
@@ -482,11 +483,9 @@ pub fn parse_bare_identifier<'i, E: ParseError<&'i str>>(
     let mut chars = input.chars();
     match chars.next() {
         Some(c) if is_initial_identifier(c) => {
-            let split_point = chars
-                .as_str()
-                .find(|c: char| !is_identifier(c))
-                .unwrap_or(0)
-                + c.len_utf8();
+            let tail = chars.as_str();
+            let split_point =
+                tail.find(|c: char| !is_identifier(c)).unwrap_or(tail.len()) + c.len_utf8();
             let (ident, tail) = input.split_at(split_point);
             Ok((tail, ident))
         }
@@ -536,6 +535,11 @@ mod test_parse_identifier {
     fn is_dash() {
         assert_eq!(typed_parse_identifier("- 10"), Ok((" 10", "-")))
     }
+
+    #[test]
+    fn at_eof() {
+        assert_eq!(typed_parse_identifier("abc"), Ok(("", "abc")))
+    }
 }
 
 // Parse a string matching u{00F1} as an escaped unicode code point
@@ -544,6 +548,7 @@ where
     E: ParseError<&'i str>,
     E: TagError<&'i str, &'static str>,
     E: FromExternalError<&'i str, CharTryFromError>,
+    E: ContextError<&'i str, &'static str>,
 {
     take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit())
         .map(|s| u32::from_str_radix(s, 16).expect("failed to parse 1-6 hex digits to a u32?"))
@@ -551,6 +556,7 @@ where
         .terminated(char('}'))
         .cut()
         .preceded_by(tag("u{"))
+        .context("unicode escape")
         .parse(input)
 }
 
@@ -559,6 +565,7 @@ where
     E: ParseError<&'i str>,
     E: TagError<&'i str, &'static str>,
     E: FromExternalError<&'i str, CharTryFromError>,
+    E: ContextError<&'i str, &'static str>,
 {
     alt((
         char('n').value('\n'),
@@ -571,6 +578,8 @@ where
         char('f').value('\u{0C}'),
         parse_unicode_escape,
     ))
+    .context("escape")
+    .cut()
     .preceded_by(char('\\'))
     .parse(input)
 }
@@ -604,6 +613,7 @@ where
     E: ParseError<&'i str>,
     E: TagError<&'i str, &'static str>,
     E: FromExternalError<&'i str, CharTryFromError>,
+    E: ContextError<&'i str, &'static str>,
 {
     alt((
         parse_unescaped_chunk.map(StringChunk::Chunk),
@@ -622,6 +632,7 @@ where
     E: ParseError<&'i str>,
     E: TagError<&'i str, &'static str>,
     E: FromExternalError<&'i str, CharTryFromError>,
+    E: ContextError<&'i str, &'static str>,
 {
     parse_separated_terminated(
         parse_chunk,
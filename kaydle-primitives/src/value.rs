@@ -7,6 +7,7 @@ use std::{
     borrow::Cow,
     char::CharTryFromError,
     fmt::{self, Formatter},
+    marker::PhantomData,
 };
 
 use nom::{
@@ -26,7 +27,7 @@ use crate::{
 
 /// An arbitrary KDL Value. See also [`AnnotatedValue`][crate::annotation::AnnotatedValue]
 /// for a value that includes an optional annotation.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GenericValue<N, S> {
     /// `null`
     Null,
@@ -80,15 +81,15 @@ impl Serialize for KdlValue<'_> {
     }
 }
 
-impl<'de> Deserialize<'de> for KdlValue<'de> {
+impl<'de: 'a, 'a> Deserialize<'de> for KdlValue<'a> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        struct ValueVisitor;
+        struct ValueVisitor<'a>(PhantomData<KdlValue<'a>>);
 
-        impl<'de> de::Visitor<'de> for ValueVisitor {
-            type Value = KdlValue<'de>;
+        impl<'de: 'a, 'a> de::Visitor<'de> for ValueVisitor<'a> {
+            type Value = KdlValue<'a>;
 
             fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
                 write!(formatter, "a KDL value")
@@ -151,16 +152,16 @@ impl<'de> Deserialize<'de> for KdlValue<'de> {
             }
         }
 
-        deserializer.deserialize_any(ValueVisitor)
+        deserializer.deserialize_any(ValueVisitor(PhantomData))
     }
 
     fn deserialize_in_place<D>(deserializer: D, place: &mut Self) -> Result<(), D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        struct ValueVisitor<'de, 'a>(&'a mut KdlValue<'de>);
+        struct ValueVisitor<'a, 'p>(&'p mut KdlValue<'a>);
 
-        impl<'de> de::Visitor<'de> for ValueVisitor<'de, '_> {
+        impl<'de: 'a, 'a> de::Visitor<'de> for ValueVisitor<'a, '_> {
             type Value = ();
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
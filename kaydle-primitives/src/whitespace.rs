@@ -5,7 +5,7 @@ they always return `()`
 
 use nom::{
     branch::alt,
-    character::complete::char,
+    character::complete::{char, satisfy},
     combinator::eof,
     error::{make_error, ErrorKind, ParseError},
     Err as NomErr, IResult, Parser,
@@ -22,13 +22,16 @@ enum BlockCommentTag {
     End,
 }
 
-/// Parse the part of a multi line comment that comes after the /*. Operates
-/// recursively.
+/// Parse the part of a multi line comment that comes after the /*. Nested
+/// comments are tracked with a counter rather than by recursion, so deeply
+/// nested comments can't overflow the stack.
 fn finish_block_comment<'i, E>(mut input: &'i str) -> IResult<&'i str, (), E>
 where
     E: ParseError<&'i str>,
     E: TagError<&'i str, &'static str>,
 {
+    let mut depth: usize = 1;
+
     loop {
         let event = input
             .as_bytes()
@@ -47,10 +50,17 @@ where
                     E::from_tag("", "*/"),
                 )))
             }
-            Some((i, BlockCommentTag::End)) => return Ok((&input[i + 2..], ())),
+            Some((i, BlockCommentTag::End)) => {
+                input = &input[i + 2..];
+                depth -= 1;
+
+                if depth == 0 {
+                    return Ok((input, ()));
+                }
+            }
             Some((i, BlockCommentTag::Start)) => {
-                let (tail, ()) = finish_block_comment(&input[i + 2..])?;
-                input = tail;
+                input = &input[i + 2..];
+                depth += 1;
             }
         }
     }
@@ -65,6 +75,22 @@ where
     tag("/*").precedes(finish_block_comment).parse(input)
 }
 
+/// Returns true if this is a plain (non-newline) whitespace character,
+/// including the byte order mark.
+pub fn is_plain_whitespace(c: char) -> bool {
+    match c {
+        // Whitespace
+        '\u{0009}' | '\u{0020}' | '\u{00A0}' | '\u{1680}' => true,
+        '\u{2000}'..='\u{200A}' => true,
+        '\u{202F}' | '\u{205F}' | '\u{3000}' => true,
+
+        // BOM
+        '\u{FFEF}' => true,
+
+        _ => false,
+    }
+}
+
 /// Parse any amount (1 or more) of plain non-newline whitespace. Includes
 /// "real" whitespace, bom, and multiline comments
 pub fn parse_plain_whitespace<'i, E>(input: &'i str) -> IResult<&'i str, (), E>
@@ -72,31 +98,9 @@ where
     E: ParseError<&'i str> + TagError<&'i str, &'static str>,
 {
     at_least_one(
-        alt((
-            // Whitespace
-            char('\u{0009}'),
-            char('\u{0020}'),
-            char('\u{00A0}'),
-            char('\u{1680}'),
-            char('\u{2000}'),
-            char('\u{2001}'),
-            char('\u{2002}'),
-            char('\u{2003}'),
-            char('\u{2004}'),
-            char('\u{2005}'),
-            char('\u{2006}'),
-            char('\u{2007}'),
-            char('\u{2008}'),
-            char('\u{2009}'),
-            char('\u{200A}'),
-            char('\u{202F}'),
-            char('\u{205F}'),
-            char('\u{3000}'),
-            // BOM
-            char('\u{FFEF}'),
-        ))
-        .value(())
-        .or(parse_block_comment),
+        satisfy(is_plain_whitespace)
+            .value(())
+            .or(parse_block_comment),
     )
     .parse(input)
 }
@@ -120,7 +124,8 @@ where
     .parse(input)
 }
 
-fn is_newline(c: char) -> bool {
+/// Returns true if this is a newline character
+pub fn is_newline(c: char) -> bool {
     ['\r', '\n', '\u{85}', '\u{0C}', '\u{2028}', '\u{2029}'].contains(&c)
 }
 
@@ -9,9 +9,11 @@ use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
 #[serde(rename = "item")]
+#[allow(dead_code)]
 struct Item(i32, i32, char);
 
 #[derive(Deserialize, Debug)]
+#[allow(dead_code)]
 enum Enum {
     #[serde(rename = "int")]
     Int(i32),
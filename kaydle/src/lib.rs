@@ -3,6 +3,7 @@
 early alpha. See [`serde`] for details.
  */
 #![deny(missing_docs)]
+#![forbid(unsafe_code)]
 
 /// Serde components for kaydle
 pub mod serde;
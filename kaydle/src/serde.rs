@@ -1,4 +1,6 @@
 pub mod de;
 mod magics;
+pub mod registry;
 
-pub use de::from_str;
+pub use de::{from_str, from_str_with_options};
+pub use registry::NodeRegistry;
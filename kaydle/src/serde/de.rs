@@ -72,8 +72,7 @@ let document: Document = kaydle::serde::from_str(r#"
     }
 
     // kaydle treats properties and children similarly when deserializing maps.
-    // in the future it will be possible to specify which one you wanted, or
-    // use both.
+    // Options::properties_and_children can also merge them.
     properties int=10 string="world"
     children {
         int 10
@@ -141,54 +140,445 @@ At a high level, kaydle treats KDL content as being made of up 3 different
 data structures:
 
 - A **node list** is a list of nodes- either a Document or Children. It maps to
-the serde data model as either a **sequence** of **named nodes** or a **mapping**
-of strings to **anonymous nodes**
+  the serde data model as either a **sequence** of **named nodes** or a **mapping**
+  of strings to **anonymous nodes**
 - A **named node** is a node that still has its name associated with it. Usually
-named nodes appear when deserializing a **node list** as a sequence. Kaydle
-required that named nodes "use" their name in some way- either the node name
-must match the name of the deserialized type, or (if it's an enum) it's used
-as the enum discriminant. A node name of "-" is treated the same as an
-**anonymous node**
+  named nodes appear when deserializing a **node list** as a sequence. Kaydle
+  required that named nodes "use" their name in some way- either the node name
+  must match the name of the deserialized type, or (if it's an enum) it's used
+  as the enum discriminant. A node name of "-" is treated the same as an
+  **anonymous node**
 - An **anonymous node** is a node that doesn't have its name associated with it.
-This can happen because the node list is being deserialized as a mapping (so
-the name was used as a key), or because the name was used as an enum
-discriminant. **Anonymous nodes** can be treated as (most) primitive values,
-in which case they must contain a single argument and nothing else. They can
-alternatively be treated as sequences or maps, in which case the node must
-contain *only* arguments *or* properties *or* children (in the future it will
-be possible to use specially named struct fields to extract nodes with more
-than one of these).
+  This can happen because the node list is being deserialized as a mapping (so
+  the name was used as a key), or because the name was used as an enum
+  discriminant. **Anonymous nodes** can be treated as (most) primitive values,
+  in which case they must contain a single argument and nothing else. They can
+  alternatively be treated as sequences or maps, in which case the node must
+  contain *only* arguments *or* properties *or* children (in the future it will
+  be possible to use specially named struct fields to extract nodes with more
+  than one of these). Properties and children can be used together with
+  [`Options::properties_and_children`].
 
 A KDL value maps directly to the serde data model in the ways you might expect
 (strings, booleans, null, strings, etc). Annotations are ignored by default,
 but they can be used in two places:
 - You can deserialize an annotated value into a struct containing a field called
-`$kaydle::annotation` to retrieve the value annotation. The struct should
-contain one additional field to store the value itself.
+  `$kaydle::annotation` to retrieve the value annotation. The struct should
+  contain one additional field to store the value itself.
 - You can deserialize an annotated value into a newtype enum, in which case the
-annotation is used as an enum discriminant.
+  annotation is used as an enum discriminant.
+
+Unannotated string values, as well as node names, property keys, and
+annotations, can be deserialized as unit enum variants.
+
+When a node list or a node's properties are deserialized as a map, the node
+names or property keys can also be parsed into primitive types like integers,
+floats, bools, and chars, so `HashMap<u16, String>` can be deserialized from
+children like `"80" "http"`. The same applies to the keys collected by a
+`$kaydle::keyed::` field, when they're strings.
+
+Node annotations can be used in the same two ways:
+- A struct containing a `$kaydle::annotation` field will receive the node's
+  annotation. If the struct has exactly one other field, the rest of the node
+  is deserialized into it, just like with values; otherwise the other fields
+  are deserialized from the properties or children of the node.
+- If a node being deserialized into an enum has an annotation, the annotation
+  selects the variant and the rest of the node is the variant content; for a
+  named node, the node name must then match the enum's type name (or be `-`),
+  just like any other named type. Un-annotated anonymous nodes use their first
+  argument as the enum discriminant.
+
+Node lists can contain several nodes with the same name. To collect them, use
+a field called `$kaydle::repeated::<name>`; every node called `<name>` in the
+list (wherever it appears) is deserialized into that field as a sequence of
+anonymous nodes:
+
+```
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Config {
+    name: String,
+
+    #[serde(rename = "$kaydle::repeated::plugin", default)]
+    plugins: Vec<String>,
+}
+
+let config: Config = kaydle::serde::from_str(r#"
+    plugin "auth"
+    name "server"
+    plugin "logging"
+"#).unwrap();
+
+assert_eq!(config, Config {
+    name: "server".to_owned(),
+    plugins: vec!["auth".to_owned(), "logging".to_owned()],
+});
+```
+
+Similarly, a field called `$kaydle::keyed::<name>` collects every node called
+`<name>` into a map. The first argument of each node is used as the key, and
+the rest of the node is deserialized as the value:
+
+```
+use std::collections::HashMap;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Server {
+    port: u16,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Config {
+    #[serde(rename = "$kaydle::keyed::server")]
+    servers: HashMap<String, Server>,
+}
+
+let config: Config = kaydle::serde::from_str(r#"
+    server "alpha" port=80
+    server "beta" port=8080
+"#).unwrap();
+
+assert_eq!(config.servers["alpha"], Server { port: 80 });
+assert_eq!(config.servers["beta"], Server { port: 8080 });
+```
+
+Finally, a field called `$kaydle::rest` collects everything that wasn't
+deserialized into another field. For a node with properties, it receives the
+unknown properties; for a node list, it receives the unknown nodes (keyed by
+name). This allows a struct to have a fixed set of known fields alongside
+free-form extensions:
+
+```
+use std::collections::HashMap;
+use kaydle_primitives::value::KdlValue;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+struct Plugin<'a> {
+    name: String,
+
+    #[serde(rename = "$kaydle::rest", borrow)]
+    extensions: HashMap<String, KdlValue<'a>>,
+}
+
+let plugin: Plugin = kaydle::serde::from_str(r#"
+    name "auth"
+    timeout 30
+    verbose true
+"#).unwrap();
+
+assert_eq!(plugin.name, "auth");
+assert_eq!(plugin.extensions.len(), 2);
+```
+
+The values of a `$kaydle::rest` field can be any self-describing type. A
+node is deserialized into a self-describing type based on its content: a
+single argument is a primitive, several arguments are a sequence, properties
+or children are a map, and an empty node is a unit. So a DOM-like type (such
+as an untagged enum of primitives, lists, and maps) can also collect unknown
+nodes with nested children.
+
+Nodes that mix arguments, properties, and children can be deserialized with
+the `$kaydle::arguments`, `$kaydle::properties`, and `$kaydle::children`
+magics, which collect that part of the node into a single field. Whatever
+isn't collected by a magic is used for the other fields of the struct, as
+usual. Arguments and properties can appear in any order in the node:
+
+```
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename = "exec")]
+struct Exec {
+    #[serde(rename = "$kaydle::arguments")]
+    args: Vec<String>,
+    cwd: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Document {
+    exec: Exec,
+}
+
+let document: Document = kaydle::serde::from_str(r#"
+    exec "ls" cwd="/" "-la"
+"#).unwrap();
+
+assert_eq!(document.exec, Exec {
+    args: vec!["ls".to_owned(), "-la".to_owned()],
+    cwd: "/".to_owned(),
+});
+```
+
+# Byte buffers
+
+Types that deserialize from bytes, like `serde_bytes::ByteBuf` or
+`bytes::Bytes`, can be written as a string annotated with `(base64)` or
+`(hex)`, or as a list of integer arguments from 0 to 255, one per byte. All
+three of these nodes contain the same bytes:
+
+```kdl
+data (base64)"3q2+7w=="
+data (hex)"deadbeef"
+data 0xde 0xad 0xbe 0xef
+```
+
+Whitespace in encoded strings is ignored, so long buffers can be written in
+multiline strings. Plain `Vec<u8>` is deserialized as a sequence rather than
+as bytes, so it only supports the list of arguments.
+
+# Streaming
+
+Large documents, like logs with one node per line, don't need to be
+deserialized all at once. [`StreamDeserializer`] is an iterator that
+deserializes each top-level node separately, as a named node:
+
+```
+use serde::Deserialize;
+use kaydle::serde::de::StreamDeserializer;
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename = "event")]
+struct Event {
+    id: u32,
+    kind: String,
+}
+
+let log = r#"
+    event id=1 kind="login"
+    event id=2 kind="logout"
+"#;
+
+let mut events = StreamDeserializer::<Event>::new(log);
+
+assert_eq!(events.next().unwrap().unwrap(), Event { id: 1, kind: "login".to_owned() });
+assert_eq!(events.next().unwrap().unwrap(), Event { id: 2, kind: "logout".to_owned() });
+assert!(events.next().is_none());
+```
+
+# Resource limits
+
+Deserialization recurses for each level of nested children, so documents are
+checked against a set of [`Limits`][kaydle_primitives::limits::Limits] before
+they're deserialized. The default limits (used by [`from_str`] and
+[`StreamDeserializer::new`]) are suitable for untrusted input; use
+[`Options::limits`] to change them. Documents that exceed a limit fail with
+[`Error::LimitExceeded`]. The
+[`max_depth`][kaydle_primitives::limits::Limits::max_depth] limit is also
+enforced as children are deserialized, so a [`Deserializer`] created directly
+from a [`Document`][kaydle_primitives::node::Document] can't overflow the
+stack either.
+
+# Options
+
+Other choices about how documents are deserialized are made with
+[`Options`], which is a builder that can deserialize a document directly.
+[`Options::strict`] and [`Options::permissive`] are presets for careful
+checking of generated documents and for forgiving handling of hand-written
+ones. The options are described in the next sections.
+
+# Reserved type annotations
+
+The KDL spec reserves some type annotations, like `(u8)` or `(ipv4)`, for
+specific types. Normally these are treated like any other annotation, but
+with [`Options::validate_annotations`] enabled, values with a reserved
+annotation must be compatible with it, or deserialization fails with
+[`Error::ReservedAnnotation`]:
+
+```
+use kaydle::serde::de::Options;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+struct Host {
+    address: String,
+    port: u16,
+}
+
+let options = Options::new().validate_annotations(true);
+
+let host: Host = options.from_str(
+    r#"
+    address (ipv4)"10.0.0.1"
+    port (u16)8080
+    "#,
+).unwrap();
+assert_eq!(host.address, "10.0.0.1");
+
+let err = options.from_str::<Host>(
+    r#"
+    address (ipv4)"localhost"
+    port (u16)8080
+    "#,
+).unwrap_err();
+assert_eq!(
+    err.to_string(),
+    "value doesn't match its (ipv4) annotation: not an IPv4 address",
+);
+```
+
+Types with a reserved annotation implement
+[`FromReserved`](kaydle_primitives::reserved::FromReserved), which can be used
+with `#[serde(deserialize_with = "kaydle_primitives::reserved::deserialize")]`.
+The `uuid`, `url`, `regex`, and `chrono` cargo features add implementations
+for types from those crates, and use them to check the corresponding
+annotations.
+
+# Lenient scalars
+
+By default, values must already have the type being deserialized: `port
+"8080"` can't be deserialized as a `u16`. Hand-written config files often get
+this wrong, so [`Options::lenient_scalars`] converts numeric strings to
+numbers, strings like `"yes"` and `"off"` to bools, and integer-valued floats
+like `1e3` to integers:
+
+```
+use kaydle::serde::de::Options;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Server {
+    port: u16,
+    enabled: bool,
+    workers: u32,
+}
+
+let server: Server = Options::new().lenient_scalars(true).from_str(
+    r#"
+    port "8080"
+    enabled "yes"
+    workers 1e3
+    "#,
+).unwrap();
+
+assert_eq!(server, Server { port: 8080, enabled: true, workers: 1000 });
+```
+
+# Node names
+
+Named nodes, like the items in a list of structs or the nodes in a
+[`StreamDeserializer`], must have the same name as the type they're
+deserialized into (or its `#[serde(rename)]`), or deserialization fails with
+[`Error::TypeNameMismatch`]. KDL names are usually kebab-case, so
+[`Options::name_matching`] can instead match node names against the type name
+in kebab-case, in snake_case, or ignoring case. [`Options::wildcard_names`]
+makes a node called `-` (or any node) match any type:
+
+```
+use kaydle::serde::de::{NameMatching, Options, WildcardNames};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct HttpServer {
+    port: u16,
+}
+
+let options = Options::new()
+    .name_matching(NameMatching::KebabCase)
+    .wildcard_names(WildcardNames::Dash);
+
+let servers: Vec<HttpServer> = options.from_str(
+    r#"
+    http-server port=80
+    - port=443
+    "#,
+).unwrap();
+
+assert_eq!(servers, [HttpServer { port: 80 }, HttpServer { port: 443 }]);
+```
+
+# Properties and children
+
+A struct or map can be deserialized from either the properties or the
+children of a node. By default a node can't have both, but with
+[`Options::properties_and_children`] set to [`PropertiesAndChildren::Merge`]
+the properties and children are used together, properties first.
+
+The KDL spec says that when a node has several properties with the same key,
+the last one wins. By default, kaydle passes all of them to the type being
+deserialized (so a derived struct fails with a duplicate field error);
+[`Options::duplicate_properties`] can instead use the last one, as the spec
+says, or reject them with [`Error::DuplicateProperty`]:
+
+```
+use kaydle::serde::de::{DuplicateProperties, Options, PropertiesAndChildren};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Server {
+    host: String,
+    port: u16,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Config {
+    server: Server,
+}
+
+let options = Options::new()
+    .duplicate_properties(DuplicateProperties::Last)
+    .properties_and_children(PropertiesAndChildren::Merge);
+
+let config: Config = options.from_str(
+    r#"
+    server host="localhost" host="example.com" {
+        port 80
+    }
+    "#,
+).unwrap();
+
+assert_eq!(config.server, Server { host: "example.com".to_owned(), port: 80 });
+```
+
+# Node registries
+
+Lists of nodes whose types are only known at runtime, like config nodes
+registered by plugins, can be deserialized into trait objects with a
+[`NodeRegistry`](crate::serde::NodeRegistry), which maps node names to the
+types they're deserialized into. A [`DeserializeSeed`](de::DeserializeSeed)
+like a registry can be used with [`Options::from_str_seed`].
+
+# Error reporting
+
+Syntax errors and exceeded limits know where in the document they happened.
+[`Error::render`] formats an error for humans, showing the offending line
+with the error underlined, what was being parsed, and a hint for fixing it
+if there is one. With the `miette` cargo feature enabled, [`Error`][enum@Error] also
+implements [`miette::Diagnostic`](https://docs.rs/miette/latest/miette/trait.Diagnostic.html); use
+[`Report::with_source_code`](https://docs.rs/miette/latest/miette/struct.Report.html#method.with_source_code)
+to attach the document.
 
 # Unimplemented limitations
 
-- The major unimplemented thing at this point are most kaydle magics:
-`$kaydle::children`, `$kaydle::arguments`, etc. These will allow nodes
-containing mixes of children, arguments, and properties to be deserialized.
-- Anonymous nodes cannot yet be deserialized into enums. This will be handled
-by using the first argument as a discriminant.
+- The `$kaydle::name` and `$kaydle::transparent` magics aren't implemented
+  yet.
 - Anonymous nodes cannot yet be deserialized into options.
 */
 
 mod annotation;
 mod anonymous_node;
+mod bytes;
+mod content;
+mod key;
 mod named_node;
+mod naming;
 mod node_list;
+mod options;
+mod render;
+mod stream;
 mod string;
 mod util;
 mod value;
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 
-use kaydle_primitives::node::Document;
+use kaydle_primitives::{
+    error::{ContextualError, ParseError},
+    limits::LimitError,
+    reserved::ReservedError,
+};
 use serde::de;
 use thiserror::Error;
 
@@ -204,22 +594,53 @@ pub enum Error {
     #[error("can't deserialize primitive type from node list")]
     PrimitiveFromNodelist,
 
-    /// There was a parse error
-    #[error("parse error")]
-    ParseError,
+    /// The document wasn't valid KDL. When the error comes from
+    /// [`from_str`] or a [`StreamDeserializer`], its offset is a byte offset
+    /// into the input string; see [`ParseError::locate`] for other cases.
+    #[error("parse error: {0}")]
+    ParseError(#[from] ContextualError),
+
+    /// The document exceeded one of the
+    /// [`Limits`][kaydle_primitives::limits::Limits] on its size or shape
+    #[error("document exceeded a resource limit: {0}")]
+    LimitExceeded(#[from] LimitError),
+
+    /// A value with a type annotation reserved by the KDL spec wasn't
+    /// compatible with that annotation. Only checked if
+    /// [`Options::validate_annotations`] is enabled.
+    #[error(transparent)]
+    ReservedAnnotation(#[from] ReservedError),
 
     /// Didn't consume all the nodes from a document or children
-    #[error("a deserialize didn't use all the nodes in the list")]
-    UnusedNode,
+    #[error("a deserialize didn't use all the nodes in the list; {name:?} was left over")]
+    UnusedNode {
+        /// The name of the first node that wasn't used
+        name: String,
+    },
+
+    /// A node collected by a `$kaydle::keyed::` magic didn't have an argument
+    /// to use as its key
+    #[error("{name:?} node has no argument to use as its key")]
+    MissingNodeKey {
+        /// The name of the node
+        name: String,
+    },
 
     /// The node name didn't match the newtype name
-    #[error("attempted to deserialize a type called {type_name} from a node called {node_name}")]
+    #[error(
+        "attempted to deserialize a type called {type_name} from a node called {node_name}; \
+        expected a node called {expected}"
+    )]
     TypeNameMismatch {
         /// The name of the node being deserialized
         node_name: String,
 
         /// The name of the type being deserialized, which should match the node_name
         type_name: &'static str,
+
+        /// The node name that was expected, which is the type name spelled
+        /// according to [`Options::name_matching`]
+        expected: String,
     },
 
     /// The node wasn't compatible with the type being deserialized (eg, tried
@@ -238,18 +659,117 @@ pub enum Error {
     #[error("got $kaydle::annotation, but the struct must have exactly two fields")]
     InvalidAnnotatedValue,
 
-    /// The Deserialize type didn't consume the entire node
-    #[error("sequence or map deserializer didn't consume the whole node")]
-    UnfinishedNode,
+    /// The Deserialize type didn't consume the entire node. For instance, a
+    /// 2-tuple was deserialized from a node with 3 arguments.
+    #[error(
+        "sequence or map deserializer didn't consume the whole node; {leftover} was left over"
+    )]
+    UnfinishedNode {
+        /// The first part of the node that wasn't consumed
+        leftover: Leftover,
+    },
+
+    /// The node contained something that the Deserialize type couldn't use
+    /// at all. For instance, a sequence was deserialized from a node that has
+    /// properties in addition to arguments.
+    #[error("attempted to deserialize a node, but it contains {leftover}, which is incompatible with the type")]
+    IncompatibleContent {
+        /// The first part of the node that couldn't be used
+        leftover: Leftover,
+    },
 
     /// A non-newtype enum was deserialized from an annotated value
     #[error("only newtype variants can be deserialized from `(annotation)value` values")]
     NonNewtypeFromAnnotatedValue,
+
+    /// A node had more than one property with the same key, and
+    /// [`Options::duplicate_properties`] is [`DuplicateProperties::Reject`]
+    #[error("property {key:?} appears more than once in the node")]
+    DuplicateProperty {
+        /// The key of the repeated property
+        key: String,
+    },
+
+    /// A byte buffer was deserialized from a `(base64)` or `(hex)` string,
+    /// but the string wasn't valid in that encoding
+    #[error("invalid {encoding} string: {reason}")]
+    InvalidEncodedBytes {
+        /// The encoding, from the annotation
+        encoding: &'static str,
+
+        /// What was wrong with the string
+        reason: &'static str,
+    },
+
+    /// A byte buffer was deserialized from the arguments of a node, but one
+    /// of them wasn't an integer between 0 and 255
+    #[error("argument {index} isn't a byte")]
+    InvalidByte {
+        /// The index of the argument (counting from 0)
+        index: usize,
+    },
+
+    /// An enum variant (from a node name, annotation, or string) didn't match
+    /// any of the variants of the enum being deserialized
+    #[error(
+        "unknown variant {variant:?}, expected one of: {}",
+        .expected.join(", ")
+    )]
+    UnknownVariant {
+        /// The variant that was found in the document
+        variant: String,
+
+        /// The variants of the enum type being deserialized
+        expected: &'static [&'static str],
+    },
+}
+
+/// A part of a node that wasn't used by a deserialize; see
+/// [`Error::UnfinishedNode`] and [`Error::IncompatibleContent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Leftover {
+    /// The argument at this index (counting from 0)
+    Argument(usize),
+
+    /// The property with this key
+    Property(String),
+
+    /// The child node with this name
+    Child(String),
+}
+
+impl fmt::Display for Leftover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Leftover::Argument(index) => write!(f, "argument {index}"),
+            Leftover::Property(key) => write!(f, "property {key:?}"),
+            Leftover::Child(name) => write!(f, "child node {name:?}"),
+        }
+    }
 }
 
-impl From<nom::Err<()>> for Error {
-    fn from(_: nom::Err<()>) -> Self {
-        Self::ParseError
+impl From<nom::Err<ContextualError>> for Error {
+    fn from(err: nom::Err<ContextualError>) -> Self {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => Self::ParseError(err),
+
+            // The parsers are all complete, so this never happens in
+            // practice. If it did, it would mean the input ended early.
+            nom::Err::Incomplete(_) => {
+                Self::ParseError(ParseError::UnexpectedEnd { offset: 0 }.into())
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Convert the offset of a parse error into a byte offset from the start
+    /// of `input`, which is where the parser started.
+    pub(super) fn locate(self, input: &str) -> Self {
+        match self {
+            Self::ParseError(err) => Self::ParseError(err.locate(input)),
+            err => err,
+        }
     }
 }
 
@@ -260,14 +780,89 @@ impl de::Error for Error {
     {
         Self::Custom(msg.to_string())
     }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        Self::UnknownVariant {
+            variant: variant.to_owned(),
+            expected,
+        }
+    }
 }
 
 /// Deserialize something from a string containing a KDL document.
-/// See [module][crate::serde::de] docs for details
+/// See [module][crate::serde::de] docs for details. The document is checked
+/// against the default [`Limits`][kaydle_primitives::limits::Limits] before
+/// it's deserialized.
 pub fn from_str<'a, T: de::Deserialize<'a>>(input: &'a str) -> Result<T, Error> {
-    let document = Document::new(input);
-    let deserializer = node_list::Deserializer::new(document);
-    T::deserialize(deserializer)
+    Options::new().from_str(input)
 }
 
+/// Deserialize something from a string containing a KDL document, with some
+/// custom [`Options`]. The document is checked against the options'
+/// [`limits`][Options::limits] before it's deserialized. This is the same as
+/// [`Options::from_str`].
+pub fn from_str_with_options<'a, T: de::Deserialize<'a>>(
+    input: &'a str,
+    options: Options,
+) -> Result<T, Error> {
+    options.from_str(input)
+}
+
+pub use content::{DuplicateProperties, PropertiesAndChildren};
+pub use naming::{NameMatching, WildcardNames};
 pub use node_list::Deserializer;
+pub use options::Options;
+pub use render::Rendered;
+pub use stream::StreamDeserializer;
+
+#[cfg(test)]
+mod tests {
+    use kaydle_primitives::error::{ContextualError, ParseError};
+    use serde::Deserialize;
+
+    use super::{from_str, Error, StreamDeserializer};
+
+    #[test]
+    fn parse_errors() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Config {
+            name: String,
+            port: u16,
+        }
+
+        match from_str::<Config>("name \"server\"\nport 99999999999999999999\n") {
+            Err(Error::ParseError(err)) => assert_eq!(
+                err.error,
+                ParseError::NumberOutOfRange {
+                    offset: 19,
+                    target: "u64",
+                    literal: "99999999999999999999".to_owned(),
+                }
+            ),
+            result => panic!("expected a parse error, got {result:?}"),
+        }
+
+        assert!(matches!(
+            from_str::<Config>("name \"server\nport 80\n"),
+            Err(Error::ParseError(ContextualError {
+                error: ParseError::UnterminatedString { offset: 5 },
+                ..
+            }))
+        ));
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename = "a")]
+        struct A(String);
+
+        // Offsets from streams are relative to the start of the whole input
+        let mut nodes = StreamDeserializer::<A>::new("a \"x\"\na \"\\q\"\n");
+        assert_eq!(nodes.next().unwrap().unwrap(), A("x".to_owned()));
+        assert!(matches!(
+            nodes.next(),
+            Some(Err(Error::ParseError(ContextualError {
+                error: ParseError::InvalidEscape { offset: 10 },
+                ..
+            })))
+        ));
+    }
+}
@@ -7,9 +7,7 @@ use serde::{de, forward_to_deserialize_any};
 use super::string::Deserializer as StringDeserializer;
 
 /// An Annotation Deserializer. Operates on an `Option<String>`, but also
-/// accepts requests to deserialize as a string directly. May also learn how
-/// to deserialize into unit enum variants.
-
+/// accepts requests to deserialize as a string or unit enum variant directly.
 #[derive(Debug, Clone, new)]
 pub struct Deserializer<'i, E> {
     annotation: Option<KdlString<'i>>,
@@ -32,7 +30,7 @@ impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
         bytes byte_buf option newtype_struct seq tuple
-        tuple_struct map struct ignored_any enum
+        tuple_struct map struct ignored_any
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -79,4 +77,21 @@ impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
     {
         self.deserialize_str(visitor)
     }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.annotation {
+            Some(annotation) => {
+                StringDeserializer::new(annotation).deserialize_enum(name, variants, visitor)
+            }
+            None => Err(E::invalid_type(de::Unexpected::Option, &visitor)),
+        }
+    }
 }
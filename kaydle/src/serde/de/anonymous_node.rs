@@ -1,177 +1,573 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    mem,
+};
+
+use derive_new::new;
 use kaydle_primitives::{
     annotation::{Annotated, AnnotatedValue, RecognizedAnnotated, RecognizedAnnotationValue},
-    node::{DrainOutcome, NodeContent, NodeEvent, NodeList},
-    property::{Property, RecognizedProperty},
+    limits::LimitError,
+    node::{Children, NodeContent, NodeEvent, NodeList},
+    property::{GenericProperty, Property, RecognizedProperty},
+    string::KdlString,
+    value::KdlValue,
+};
+use serde::{
+    de::{
+        self,
+        value::{BorrowedStrDeserializer, MapAccessDeserializer, SeqAccessDeserializer},
+    },
+    Deserializer as _,
 };
-use serde::{de, Deserializer as _};
-use serde_mobile::SubordinateValue;
+
+use crate::serde::magics;
 
 use super::{
-    node_list, string::Deserializer as StringDeserializer, util,
-    value::annotated::Deserializer as ValueDeserializer, Error,
+    annotation::Deserializer as AnnotationDeserializer,
+    bytes,
+    key::Deserializer as KeyDeserializer,
+    node_list,
+    string::Deserializer as StringDeserializer,
+    util,
+    value::{
+        annotated::Deserializer as ValueDeserializer, raw::Deserializer as RawValueDeserializer,
+    },
+    DuplicateProperties, Error, Leftover, Options, PropertiesAndChildren,
 };
 
 #[derive(Debug)]
 pub struct Deserializer<'i, 'p> {
     node: Annotated<'i, NodeContent<'i, 'p>>,
+    options: Options,
 }
 
 impl<'i, 'p> Deserializer<'i, 'p> {
-    pub fn new(node: Annotated<'i, NodeContent<'i, 'p>>) -> Self {
-        Self { node }
+    pub fn new(node: Annotated<'i, NodeContent<'i, 'p>>, options: Options) -> Self {
+        Self { node, options }
     }
 
     /// Deserialize a single primitive value, like a number, string, unit,
     /// etc. Doesn't apply to named data. Absence of any value is handled as
-    /// a unit. Generally, in order to work correctly,
-    fn deserialize_primitive_value<V>(self, visitor: V) -> Result<V::Value, Error>
+    /// a unit. Otherwise, the value is deserialized with `deserialize`, which
+    /// is the [`ValueDeserializer`] method corresponding to the requested
+    /// type.
+    fn deserialize_primitive_value<V>(
+        self,
+        visitor: V,
+        deserialize: impl FnOnce(ValueDeserializer<'i>, V) -> Result<V::Value, Error>,
+    ) -> Result<V::Value, Error>
     where
         V: de::Visitor<'i>,
+    {
+        let mut node = BufferedNode::new(Some(self.node.item));
+
+        match node.next_argument()? {
+            Some(argument) => {
+                node.finish(Consuming::Arguments)?;
+                deserialize(ValueDeserializer::checked(argument, self.options)?, visitor)
+            }
+            None => {
+                node.finish(Consuming::Nothing)?;
+                visitor.visit_unit()
+            }
+        }
+    }
+}
+
+impl<'de> Deserializer<'de, '_> {
+    /// Deserialize the node as a map of either its properties or its
+    /// children. `annotation` is used to optionally include the node's
+    /// annotation as an additional `$kaydle::annotation` entry, before the
+    /// rest of the map. `fields` are the fields of the struct being
+    /// deserialized, if any.
+    fn deserialize_node_map<V>(
+        self,
+        annotation: AnnotationEntry<'de>,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
     {
         match self.node.item.next_event()? {
             NodeEvent::Argument {
-                argument: RecognizedAnnotationValue { item: value, .. },
-                tail,
-            } => match tail.drain()? {
-                DrainOutcome::Empty => value.visit_to(visitor),
-                DrainOutcome::NotEmpty => Err(Error::IncompatibleNode),
-            },
-            NodeEvent::Property {
-                property: RecognizedProperty { .. },
+                argument: RecognizedAnnotated { .. },
                 tail,
             } => {
                 tail.drain()?;
-                Err(Error::IncompatibleNode)
+                Err(Error::IncompatibleContent {
+                    leftover: Leftover::Argument(0),
+                })
             }
-            NodeEvent::Children { children } => match children.drain()? {
-                DrainOutcome::Empty => visitor.visit_unit(),
-                DrainOutcome::NotEmpty => Err(Error::IncompatibleNode),
-            },
-            NodeEvent::End => visitor.visit_unit(),
+            NodeEvent::Property { property, tail } => deserialize_properties_map(
+                property,
+                tail,
+                annotation,
+                fields,
+                self.options,
+                visitor,
+            ),
+            NodeEvent::Children { mut children } => {
+                check_depth(&children, self.options)?;
+
+                let value = visitor.visit_map(AnnotationMapAccess {
+                    annotation,
+                    inner: node_list::MapAccess::new(&mut children, fields, self.options),
+                })?;
+
+                finish_children(children)?;
+                Ok(value)
+            }
+            NodeEvent::End if fields.contains(&magics::REST) => {
+                visitor.visit_map(AnnotationMapAccess {
+                    annotation,
+                    inner: PropertiesMapAccess::new(BufferedNode::new(None), fields, self.options)?,
+                })
+            }
+            NodeEvent::End => visitor.visit_map(AnnotationMapAccess {
+                annotation,
+                inner: util::EmptyAccess::new(),
+            }),
+        }
+    }
+}
+
+/// Deserialize a node as a map of its properties, after the first property
+/// has been read. This is kept out of [`Deserializer::deserialize_node_map`]
+/// so that the buffered node doesn't take up stack space in every level of
+/// nested children.
+fn deserialize_properties_map<'de, 'p, V>(
+    property: Property<'de>,
+    tail: NodeContent<'de, 'p>,
+    annotation: AnnotationEntry<'de>,
+    fields: &'static [&'static str],
+    options: Options,
+    visitor: V,
+) -> Result<V::Value, Error>
+where
+    V: de::Visitor<'de>,
+{
+    let mut node = BufferedNode::new(Some(tail));
+    node.properties.push_back(property);
+
+    let children = match merges_children(options, fields) {
+        true => {
+            node.fill_all()?;
+            node.children.take()
+        }
+        false => None,
+    };
+
+    let mut access = PropertiesMapAccess::new(node, fields, options)?;
+
+    match children {
+        None => {
+            let value = visitor.visit_map(AnnotationMapAccess {
+                annotation,
+                inner: &mut access,
+            })?;
+
+            access.finish()?;
+            Ok(value)
+        }
+        Some(children) => {
+            deserialize_merged_map(access, children, annotation, fields, options, visitor)
+        }
+    }
+}
+
+/// Deserialize a node's properties and children as a single map, when they're
+/// merged. This is kept out of the deserialize methods so that the merged
+/// access doesn't take up stack space in every level of nested children.
+fn deserialize_merged_map<'de, 'p, V>(
+    properties: PropertiesMapAccess<'de, 'p>,
+    mut children: Children<'de, 'p>,
+    annotation: AnnotationEntry<'de>,
+    fields: &'static [&'static str],
+    options: Options,
+    visitor: V,
+) -> Result<V::Value, Error>
+where
+    V: de::Visitor<'de>,
+{
+    check_depth(&children, options)?;
+
+    let mut access = MergedMapAccess::new(
+        properties,
+        node_list::MapAccess::new(&mut children, fields, options),
+    );
+
+    let value = visitor.visit_map(AnnotationMapAccess {
+        annotation,
+        inner: &mut access,
+    })?;
+
+    access.properties.finish()?;
+    finish_children(children)?;
+    Ok(value)
+}
+
+/// Deserialize a struct that uses any of the `$kaydle::arguments`,
+/// `$kaydle::properties`, or `$kaydle::children` magics. The node's arguments
+/// and properties are read up front (so they can appear in any order), and
+/// whatever isn't collected by a magic is used for the struct's other fields.
+fn deserialize_magic_struct<'de, V>(
+    node: NodeContent<'de, '_>,
+    annotation: AnnotationEntry<'de>,
+    fields: &'static [&'static str],
+    options: Options,
+    visitor: V,
+) -> Result<V::Value, Error>
+where
+    V: de::Visitor<'de>,
+{
+    let mut node = BufferedNode::new(Some(node));
+    node.fill_all()?;
+    node.resolve_duplicates(options.duplicate_properties)?;
+
+    let arguments = fields
+        .contains(&magics::ARGUMENTS)
+        .then(|| mem::take(&mut node.arguments));
+
+    let properties = fields
+        .contains(&magics::PROPERTIES)
+        .then(|| mem::take(&mut node.properties));
+
+    let mut children = node.children.take();
+
+    if let Some(ref children) = children {
+        check_depth(children, options)?;
+    }
+
+    let children_magic = fields.contains(&magics::CHILDREN).then(|| children.take());
+
+    // The ordinary fields of the struct come from either the properties or
+    // the children, but not both (unless they're merged), and never the
+    // arguments. An empty set of children is allowed alongside properties.
+    if !node.arguments.is_empty() {
+        for children in [children, children_magic.flatten()].into_iter().flatten() {
+            children.drain()?;
+        }
+
+        return Err(Error::IncompatibleContent {
+            leftover: Leftover::Argument(0),
+        });
+    }
+
+    let merged = !node.properties.is_empty() && merges_children(options, fields);
+
+    if !node.properties.is_empty() && !merged {
+        if let Some(name) = children
+            .take()
+            .map(node_list::drain_unused)
+            .transpose()?
+            .flatten()
+        {
+            if let Some(Some(children)) = children_magic {
+                children.drain()?;
+            }
+
+            return Err(Error::IncompatibleContent {
+                leftover: Leftover::Child(name),
+            });
         }
     }
+
+    let mut access = MagicMapAccess {
+        arguments,
+        properties,
+        children: children_magic,
+        fields: match children {
+            Some(ref mut children) if merged => {
+                FieldsAccess::Merged(Box::new(MergedMapAccess::new(
+                    PropertiesMapAccess::new(node, fields, options)?,
+                    node_list::MapAccess::new(children, fields, options),
+                )))
+            }
+            Some(ref mut children) => {
+                FieldsAccess::Children(node_list::MapAccess::new(children, fields, options))
+            }
+            None => FieldsAccess::Properties(PropertiesMapAccess::new(node, fields, options)?),
+        },
+        value: None,
+        options,
+    };
+
+    let value = visitor.visit_map(AnnotationMapAccess {
+        annotation,
+        inner: &mut access,
+    });
+
+    // Make sure any unvisited children are still drained, even if there was
+    // an error
+    if let Some(Some(children)) = access.children {
+        children.drain()?;
+    }
+
+    if let Some(MagicValue::Children(Some(children))) = access.value {
+        children.drain()?;
+    }
+
+    let value = value?;
+
+    match access.fields {
+        FieldsAccess::Properties(access) => access.finish()?,
+        FieldsAccess::Children(access) => {
+            drop(access);
+            finish_children(children.expect("children must exist if they were used for fields"))?;
+        }
+        FieldsAccess::Merged(access) => {
+            access.properties.finish()?;
+            finish_children(children.expect("children must exist if they were merged"))?;
+        }
+    }
+
+    Ok(value)
+}
+
+/// Check if the children of a node with properties should be merged into the
+/// map of its properties, rather than rejected. See
+/// [`PropertiesAndChildren::Merge`].
+fn merges_children(options: Options, fields: &'static [&'static str]) -> bool {
+    options.properties_and_children == PropertiesAndChildren::Merge
+        && !fields.contains(&magics::REST)
+}
+
+/// Check that a node's children aren't nested more deeply than the
+/// [`max_depth`][kaydle_primitives::limits::Limits::max_depth] limit, before
+/// they're deserialized. Documents are usually checked against the limits up
+/// front, but a [`node_list::Deserializer`] can also be created directly, and
+/// deserializing its children recurses once for each level of nesting.
+fn check_depth(children: &Children<'_, '_>, options: Options) -> Result<(), Error> {
+    let limit = options.limits.max_depth;
+
+    match children.depth() > limit {
+        false => Ok(()),
+        true => Err(Error::LimitExceeded(LimitError::Depth {
+            limit,
+            offset: children.offset(),
+        })),
+    }
+}
+
+/// Drain the children of a node after they were deserialized, and make sure
+/// they were all consumed.
+fn finish_children<'i>(children: Children<'i, '_>) -> Result<(), Error> {
+    match node_list::drain_unused(children)? {
+        None => Ok(()),
+        Some(name) => Err(Error::UnfinishedNode {
+            leftover: Leftover::Child(name),
+        }),
+    }
 }
 
 impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     type Error = Error;
 
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::IncompatibleNode)
+        // Self-describing types (like `KdlValue`, or untagged enums) have no
+        // way to request anything more specific, so the shape comes from the
+        // node itself: a single argument is a primitive, several arguments
+        // are a sequence, properties or children are a map, and an empty
+        // node is a unit.
+        let options = self.options;
+        let mut node = BufferedNode::new(Some(self.node.item));
+        node.fill_all()?;
+
+        match node.arguments.len() {
+            0 if node.properties.is_empty() => match node.children.take() {
+                // An empty set of children is the same as no children
+                Some(children) if node_list::is_empty(&children)? => {
+                    children.drain()?;
+                    visitor.visit_unit()
+                }
+                Some(mut children) => {
+                    check_depth(&children, options)?;
+
+                    let value = visitor.visit_map(node_list::MapAccess::new(
+                        &mut children,
+                        &[],
+                        options,
+                    ))?;
+                    finish_children(children)?;
+                    Ok(value)
+                }
+                None => visitor.visit_unit(),
+            },
+            0 => match merges_children(options, &[])
+                .then(|| node.children.take())
+                .flatten()
+            {
+                Some(children) => deserialize_merged_map(
+                    PropertiesMapAccess::new(node, &[], options)?,
+                    children,
+                    AnnotationEntry::Done,
+                    &[],
+                    options,
+                    visitor,
+                ),
+                None => {
+                    let mut access = PropertiesMapAccess::new(node, &[], options)?;
+                    let value = visitor.visit_map(&mut access)?;
+                    access.finish()?;
+                    Ok(value)
+                }
+            },
+            1 => {
+                let argument = node.next_argument()?.expect("node has 1 argument");
+                node.finish(Consuming::Arguments)?;
+                ValueDeserializer::checked(argument, options)?.deserialize_any(visitor)
+            }
+            _ => {
+                let mut access = ArgumentsSeqAccess { node, options };
+                let value = visitor.visit_seq(&mut access)?;
+                access.node.finish(Consuming::Arguments)?;
+                Ok(value)
+            }
+        }
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_bool)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_i8)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_i16)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_i32)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_i64)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_u8)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_u16)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_u32)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_u64)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_f32)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_f64)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_char)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_str)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_string)
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_byte_buf(visitor)
     }
 
+    /// Bytes can come from a single value (like a `(base64)` string), or
+    /// from a list of numeric arguments, one per byte. A node without any
+    /// arguments is an empty buffer.
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        let mut node = BufferedNode::new(Some(self.node.item));
+
+        let first = match node.next_argument()? {
+            Some(argument) => argument,
+            None => {
+                node.finish(Consuming::Nothing)?;
+                return visitor.visit_byte_buf(Vec::new());
+            }
+        };
+
+        self.options.check_value(&first)?;
+
+        if !matches!(first.item, KdlValue::Number(..)) {
+            node.finish(Consuming::Arguments)?;
+            return ValueDeserializer::new(first, self.options).deserialize_byte_buf(visitor);
+        }
+
+        let mut buffer = vec![bytes::from_argument(0, &first.item)?];
+
+        while let Some(argument) = node.next_argument()? {
+            self.options.check_value(&argument)?;
+            buffer.push(bytes::from_argument(buffer.len(), &argument.item)?);
+        }
+
+        node.finish(Consuming::Arguments)?;
+        visitor.visit_byte_buf(buffer)
     }
 
     fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -191,7 +587,7 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_unit)
     }
 
     fn deserialize_unit_struct<V>(
@@ -207,18 +603,16 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
 
     fn deserialize_newtype_struct<V>(
         self,
-        name: &'static str,
-        _visitor: V,
+        _name: &'static str,
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        // TODO: consider handling newtype structs as single element tuples?
-        todo!(
-            "Deserializing nodes into newtype structs isn't implemented yet \
-            (did you mean to include #[serde(transparent)] on type {:?}?",
-            name
-        )
+        // Newtypes are transparent, so the node is forwarded to the inner
+        // type. For single-argument nodes, this is equivalent to treating the
+        // newtype as a 1-tuple.
+        visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -228,40 +622,33 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
         // Basic logic: a sequence is either of arguments or children.
         match self.node.item.next_event()? {
             NodeEvent::Argument { argument, tail } => {
+                let mut node = BufferedNode::new(Some(tail));
+                node.arguments.push_back(argument);
+
                 let mut access = ArgumentsSeqAccess {
-                    peeked: Some(argument),
-                    node: Some(tail),
+                    node,
+                    options: self.options,
                 };
-
                 let value = visitor.visit_seq(&mut access)?;
-
-                match access.peeked {
-                    Some(..) => Err(Error::UnfinishedNode),
-                    None => match access.node {
-                        None => Ok(value),
-                        Some(node) => match node.drain()? {
-                            DrainOutcome::Empty => Ok(value),
-
-                            // TODO: Need to inspect here to distinguish
-                            // Unfinished from Incompatible
-                            DrainOutcome::NotEmpty => Err(Error::IncompatibleNode),
-                        },
-                    },
-                }
+                access.node.finish(Consuming::Arguments)?;
+                Ok(value)
             }
             NodeEvent::Property {
-                property: RecognizedProperty { .. },
+                property: GenericProperty::<KdlString, (), ()> { key, .. },
                 tail,
             } => {
                 tail.drain()?;
-                Err(Error::IncompatibleNode)
+                Err(Error::IncompatibleContent {
+                    leftover: Leftover::Property(key.into_string()),
+                })
             }
             NodeEvent::Children { mut children } => {
-                let value = visitor.visit_seq(node_list::SeqAccess::new(&mut children))?;
-                match children.drain()? {
-                    DrainOutcome::Empty => Ok(value),
-                    DrainOutcome::NotEmpty => Err(Error::UnfinishedNode),
-                }
+                check_depth(&children, self.options)?;
+
+                let value =
+                    visitor.visit_seq(node_list::SeqAccess::new(&mut children, self.options))?;
+                finish_children(children)?;
+                Ok(value)
             }
             NodeEvent::End => visitor.visit_seq(util::EmptyAccess::new()),
         }
@@ -290,56 +677,11 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     where
         V: de::Visitor<'de>,
     {
-        match self.node.item.next_event()? {
-            NodeEvent::Argument {
-                argument: RecognizedAnnotated { .. },
-                tail,
-            } => {
-                tail.drain()?;
-                Err(Error::IncompatibleNode)
-            }
-            NodeEvent::Property { property, tail } => {
-                use serde_mobile::AccessAdapter;
-
-                let mut access = AccessAdapter::new(PropertiesMapAccess {
-                    node: tail,
-                    peeked: Some(property),
-                });
-
-                let value = visitor.visit_map(&mut access)?;
-
-                // If empty is false, the node was definitely not fully
-                // consumed. It still needs to be drained, but even if the
-                // drain is empty it's already too late.
-                let (empty, tail) = match access {
-                    AccessAdapter::Done => return Ok(value),
-                    AccessAdapter::Ready(PropertiesMapAccess { peeked, node }) => {
-                        (peeked.is_none(), node)
-                    }
-                    AccessAdapter::Value(SubordinateValue {
-                        parent: PropertiesMapAccess { node, .. },
-                        ..
-                    }) => (true, node),
-                };
-
-                match tail.drain()? {
-                    DrainOutcome::Empty if empty => Ok(value),
-                    _ => Err(Error::UnfinishedNode),
-                }
-            }
-            NodeEvent::Children { mut children } => {
-                let value = visitor.visit_map(node_list::MapAccess::new(&mut children))?;
-                match children.drain()? {
-                    DrainOutcome::Empty => Ok(value),
-                    DrainOutcome::NotEmpty => Err(Error::UnfinishedNode),
-                }
-            }
-            NodeEvent::End => visitor.visit_map(util::EmptyAccess::new()),
-        }
+        self.deserialize_node_map(AnnotationEntry::Done, &[], visitor)
     }
 
     fn deserialize_struct<V>(
-        self,
+        mut self,
         name: &'static str,
         fields: &'static [&'static str],
         visitor: V,
@@ -347,38 +689,83 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     where
         V: de::Visitor<'de>,
     {
-        if let Some(field) = fields.iter().find(|field| field.starts_with("$kaydle::")) {
-            todo!(
-                "kaydle magics aren't implemented yet; \
-                found {:?} on on type {:?})",
-                field,
-                name
-            );
+        if let Some(field) = fields.iter().find(|&&field| {
+            field.starts_with("$kaydle::")
+                && ![
+                    magics::ANNOTATION,
+                    magics::ARGUMENTS,
+                    magics::PROPERTIES,
+                    magics::CHILDREN,
+                    magics::REST,
+                ]
+                .contains(&field)
+                && !field.starts_with(magics::REPEATED)
+                && !field.starts_with(magics::KEYED)
+        }) {
+            return Err(de::Error::custom(format!(
+                "unknown kaydle magic {field:?} on {name}"
+            )));
+        }
+
+        if [magics::ARGUMENTS, magics::PROPERTIES, magics::CHILDREN]
+            .iter()
+            .any(|magic| fields.contains(magic))
+        {
+            let Annotated { annotation, item } = self.node;
+
+            let annotation = match fields.contains(&magics::ANNOTATION) {
+                true => AnnotationEntry::Key(annotation),
+                false => AnnotationEntry::Done,
+            };
+
+            return deserialize_magic_struct(item, annotation, fields, self.options, visitor);
+        }
+
+        match *fields {
+            // Like with annotated values, a struct with an annotation and
+            // exactly one other field gets the rest of the node in that field
+            [magics::ANNOTATION, field_name] | [field_name, magics::ANNOTATION] => {
+                let Annotated { annotation, item } = self.node;
+
+                visitor.visit_map(AnnotationMapAccess {
+                    annotation: AnnotationEntry::Key(annotation),
+                    inner: FieldMapAccess::Key {
+                        field_name,
+                        node: Deserializer::new(
+                            Annotated {
+                                annotation: None,
+                                item,
+                            },
+                            self.options,
+                        ),
+                    },
+                })
+            }
+            _ if fields.contains(&magics::ANNOTATION) => {
+                let annotation = self.node.annotation.take();
+                self.deserialize_node_map(AnnotationEntry::Key(annotation), fields, visitor)
+            }
+            _ => self.deserialize_node_map(AnnotationEntry::Done, fields, visitor),
         }
-        self.deserialize_map(visitor)
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
         _variants: &'static [&'static str],
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        todo!(
-            "Deserializing anonymous nodes into enums isn't implemented yet \
-            (did you mean to add #[serde(transparent)] to an enclosing \
-            newtype struct?)"
-        )
+        visitor.visit_enum(EnumAccess::new(self.node, self.options))
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_primitive_value(visitor)
+        self.deserialize_primitive_value(visitor, ValueDeserializer::deserialize_identifier)
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -424,97 +811,1412 @@ impl<'de> de::VariantAccess<'de> for Deserializer<'de, '_> {
     }
 }
 
-/// Type providing sequence access to the arguments of a node
-// TODO: replace a struct containing options
-// TODO: opt-in property / children caching.
-struct ArgumentsSeqAccess<'i, 'a> {
-    peeked: Option<AnnotatedValue<'i>>,
-    node: Option<NodeContent<'i, 'a>>,
+/// EnumAccess for an anonymous node. If the node has an annotation, it's used
+/// as the enum variant, and the rest of the node is the variant content.
+/// Otherwise, the first argument of the node is used as the variant.
+#[derive(new)]
+struct EnumAccess<'i, 'p> {
+    node: Annotated<'i, NodeContent<'i, 'p>>,
+    options: Options,
 }
 
-impl<'de, 'a> de::SeqAccess<'de> for ArgumentsSeqAccess<'de, 'a> {
+impl<'de, 'p> de::EnumAccess<'de> for EnumAccess<'de, 'p> {
     type Error = Error;
+    type Variant = Deserializer<'de, 'p>;
 
-    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
     where
-        T: de::DeserializeSeed<'de>,
+        V: de::DeserializeSeed<'de>,
     {
-        match self.peeked.take() {
-            Some(argument) => seed.deserialize(ValueDeserializer::new(argument)).map(Some),
-            None => match self.node.take() {
-                None => Ok(None),
-                Some(node) => match node.next_event()? {
-                    NodeEvent::Argument { argument, tail } => {
-                        self.node = Some(tail);
-                        seed.deserialize(ValueDeserializer::new(argument)).map(Some)
-                    }
-                    NodeEvent::Property {
-                        property: RecognizedProperty { .. },
-                        tail,
-                    } => {
-                        self.node = Some(tail);
-
-                        // This is where the buffering needs to happen
-                        Err(Error::IncompatibleNode)
-                    }
-                    NodeEvent::Children { children } => match children.drain()? {
-                        DrainOutcome::Empty => Ok(None),
-                        DrainOutcome::NotEmpty => Err(Error::IncompatibleNode),
-                    },
-                    NodeEvent::End => Ok(None),
-                },
+        let Annotated { annotation, item } = self.node;
+
+        let (variant, content) = match annotation {
+            Some(annotation) => seed
+                .deserialize(StringDeserializer::new(annotation))
+                .map(|variant| (variant, item)),
+            None => match item.next_event()? {
+                NodeEvent::Argument {
+                    argument: RecognizedAnnotationValue { item: value, .. },
+                    tail,
+                } => seed
+                    .deserialize(RawValueDeserializer::new(value))
+                    .map(|variant| (variant, tail)),
+                NodeEvent::Property {
+                    property: RecognizedProperty { .. },
+                    tail,
+                } => {
+                    tail.drain()?;
+                    Err(Error::IncompatibleNode)
+                }
+                NodeEvent::Children { children } => {
+                    children.drain()?;
+                    Err(Error::IncompatibleNode)
+                }
+                NodeEvent::End => Err(Error::IncompatibleNode),
             },
+        }?;
+
+        Ok((
+            variant,
+            Deserializer::new(
+                Annotated {
+                    annotation: None,
+                    item: content,
+                },
+                self.options,
+            ),
+        ))
+    }
+}
+
+/// The state of the `$kaydle::annotation` entry in an [`AnnotationMapAccess`]
+enum AnnotationEntry<'i> {
+    /// The annotation entry hasn't been visited yet
+    Key(Option<KdlString<'i>>),
+
+    /// The annotation key was visited; the value is next
+    Value(Option<KdlString<'i>>),
+
+    /// The annotation entry was already visited, or was never requested. All
+    /// further access is forwarded to the inner map.
+    Done,
+}
+
+/// MapAccess that (optionally) yields a node's annotation as a
+/// `$kaydle::annotation` entry, then forwards to some other MapAccess for
+/// the rest of the node.
+struct AnnotationMapAccess<'i, A> {
+    annotation: AnnotationEntry<'i>,
+    inner: A,
+}
+
+impl<'de, A> de::MapAccess<'de> for AnnotationMapAccess<'de, A>
+where
+    A: de::MapAccess<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match mem::replace(&mut self.annotation, AnnotationEntry::Done) {
+            AnnotationEntry::Key(annotation) => {
+                self.annotation = AnnotationEntry::Value(annotation);
+                seed.deserialize(BorrowedStrDeserializer::new(magics::ANNOTATION))
+                    .map(Some)
+            }
+            AnnotationEntry::Value(..) => panic!("called next_key_seed out of order"),
+            AnnotationEntry::Done => self.inner.next_key_seed(seed),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match mem::replace(&mut self.annotation, AnnotationEntry::Done) {
+            AnnotationEntry::Value(annotation) => {
+                seed.deserialize(AnnotationDeserializer::new(annotation))
+            }
+            AnnotationEntry::Key(..) => panic!("called next_value_seed out of order"),
+            AnnotationEntry::Done => self.inner.next_value_seed(seed),
         }
     }
 }
 
-struct PropertiesMapAccess<'i, 'a> {
-    peeked: Option<Property<'i>>,
-    node: NodeContent<'i, 'a>,
+/// MapAccess containing a single field, which is the rest of the node. Used
+/// for structs containing a `$kaydle::annotation` and exactly one other
+/// field.
+enum FieldMapAccess<'i, 'p> {
+    Key {
+        field_name: &'static str,
+        node: Deserializer<'i, 'p>,
+    },
+    Value {
+        node: Deserializer<'i, 'p>,
+    },
+    Done,
 }
 
-impl<'de, 'a> serde_mobile::MapKeyAccess<'de> for PropertiesMapAccess<'de, 'a> {
+impl<'de> de::MapAccess<'de> for FieldMapAccess<'de, '_> {
     type Error = Error;
-    type Value = serde_mobile::SubordinateValue<ValueDeserializer<'de>, Self>;
 
-    fn next_key_seed<S>(self, seed: S) -> Result<Option<(S::Value, Self::Value)>, Self::Error>
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
     where
-        S: de::DeserializeSeed<'de>,
+        K: de::DeserializeSeed<'de>,
     {
-        let (property, tail) = match self.peeked {
-            Some(property) => (property, self.node),
-            None => match self.node.next_event()? {
-                NodeEvent::Argument {
-                    argument: RecognizedAnnotated { .. },
-                    tail,
-                } => {
-                    // Need to buffer the argument here, then loop
-                    tail.drain()?;
-                    return Err(Error::IncompatibleNode);
-                }
-                NodeEvent::Property { property, tail } => (property, tail),
-                NodeEvent::Children { children } => {
-                    return match children.drain()? {
-                        DrainOutcome::Empty => Ok(None),
-                        DrainOutcome::NotEmpty => Err(Error::IncompatibleNode),
+        match mem::replace(self, FieldMapAccess::Done) {
+            FieldMapAccess::Key { field_name, node } => {
+                *self = FieldMapAccess::Value { node };
+                seed.deserialize(BorrowedStrDeserializer::new(field_name))
+                    .map(Some)
+            }
+            FieldMapAccess::Value { .. } => panic!("called next_key_seed out of order"),
+            FieldMapAccess::Done => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match mem::replace(self, FieldMapAccess::Done) {
+            FieldMapAccess::Value { node } => seed.deserialize(node),
+            _ => panic!("called next_value_seed out of order"),
+        }
+    }
+}
+
+/// The content of a node, with its arguments and properties buffered
+/// separately. This allows arguments and properties to be freely interleaved:
+/// for instance, reading the arguments of `exec "ls" cwd="/" "-la"` buffers
+/// the `cwd` property until it's requested (or until the node is finished).
+struct BufferedNode<'i, 'p> {
+    arguments: VecDeque<AnnotatedValue<'i>>,
+
+    /// The number of arguments that have been consumed so far, used to
+    /// report the index of leftover arguments
+    arguments_consumed: usize,
+
+    properties: VecDeque<Property<'i>>,
+
+    /// The rest of the node, if there might be more arguments or properties
+    node: Option<NodeContent<'i, 'p>>,
+
+    /// The children of the node, once they've been reached
+    children: Option<Children<'i, 'p>>,
+}
+
+/// The kind of content that a deserialize consumes from a node. When a node
+/// is finished, leftovers of this kind mean that the node is unfinished, while
+/// leftovers of any other kind mean that the node is incompatible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Consuming {
+    Nothing,
+    Arguments,
+    Properties,
+
+    /// Children are never consumed through a [`BufferedNode`], but leftover
+    /// children are still reported.
+    Children,
+}
+
+impl<'i, 'p> BufferedNode<'i, 'p> {
+    fn new(node: Option<NodeContent<'i, 'p>>) -> Self {
+        Self {
+            arguments: VecDeque::new(),
+            arguments_consumed: 0,
+            properties: VecDeque::new(),
+            node,
+            children: None,
+        }
+    }
+
+    /// Read the next argument or property from the node into the buffers.
+    /// Returns false if there are no more arguments or properties.
+    fn fill(&mut self) -> Result<bool, Error> {
+        let node = match self.node.take() {
+            None => return Ok(false),
+            Some(node) => node,
+        };
+
+        match node.next_event()? {
+            NodeEvent::Argument { argument, tail } => {
+                self.arguments.push_back(argument);
+                self.node = Some(tail);
+                Ok(true)
+            }
+            NodeEvent::Property { property, tail } => {
+                self.properties.push_back(property);
+                self.node = Some(tail);
+                Ok(true)
+            }
+            NodeEvent::Children { children } => {
+                self.children = Some(children);
+                Ok(false)
+            }
+            NodeEvent::End => Ok(false),
+        }
+    }
+
+    /// Read all the remaining arguments and properties into the buffers.
+    fn fill_all(&mut self) -> Result<(), Error> {
+        while self.fill()? {}
+        Ok(())
+    }
+
+    fn next_argument(&mut self) -> Result<Option<AnnotatedValue<'i>>, Error> {
+        loop {
+            if let Some(argument) = self.arguments.pop_front() {
+                self.arguments_consumed += 1;
+                break Ok(Some(argument));
+            }
+
+            if !self.fill()? {
+                break Ok(None);
+            }
+        }
+    }
+
+    fn next_property(&mut self) -> Result<Option<Property<'i>>, Error> {
+        loop {
+            if let Some(property) = self.properties.pop_front() {
+                break Ok(Some(property));
+            }
+
+            if !self.fill()? {
+                break Ok(None);
+            }
+        }
+    }
+
+    /// Read all the properties of the node, and resolve any that have the
+    /// same key, according to `duplicates`.
+    fn resolve_duplicates(&mut self, duplicates: DuplicateProperties) -> Result<(), Error> {
+        if duplicates == DuplicateProperties::Keep {
+            return Ok(());
+        }
+
+        self.fill_all()?;
+
+        let mut seen = HashSet::new();
+
+        match duplicates {
+            DuplicateProperties::Keep => {}
+            DuplicateProperties::Last => {
+                // Keep each property only if no later property has its key
+                let keep: Vec<bool> = self
+                    .properties
+                    .iter()
+                    .rev()
+                    .map(|property| seen.insert(property.key.as_str()))
+                    .collect();
+
+                let mut keep = keep.into_iter().rev();
+                self.properties.retain(|_| keep.next().unwrap_or(true));
+            }
+            DuplicateProperties::Reject => {
+                if let Some(property) = self
+                    .properties
+                    .iter()
+                    .find(|property| !seen.insert(property.key.as_str()))
+                {
+                    let key = property.key.clone().into_string();
+
+                    if let Some(children) = self.children.take() {
+                        children.drain()?;
                     }
+
+                    return Err(Error::DuplicateProperty { key });
                 }
-                NodeEvent::End => return Ok(None),
-            },
-        };
+            }
+        }
 
-        seed.deserialize(StringDeserializer::new(property.key))
-            .map(|deserialized| {
-                Some((
-                    deserialized,
-                    serde_mobile::SubordinateValue {
-                        parent: PropertiesMapAccess {
-                            peeked: None,
-                            node: tail,
-                        },
-                        value: ValueDeserializer::new(property.value),
-                    },
-                ))
+        Ok(())
+    }
+
+    /// Read the rest of the node, including its children, and make sure that
+    /// nothing was left over. `consuming` is the kind of content that the
+    /// caller was consuming, which determines if leftovers are reported as
+    /// [`Error::UnfinishedNode`] or [`Error::IncompatibleContent`].
+    fn finish(mut self, consuming: Consuming) -> Result<(), Error> {
+        self.fill_all()?;
+
+        let child = self
+            .children
+            .map(node_list::drain_unused)
+            .transpose()?
+            .flatten();
+
+        let (kind, leftover) = if !self.arguments.is_empty() {
+            (
+                Consuming::Arguments,
+                Leftover::Argument(self.arguments_consumed),
+            )
+        } else if let Some(property) = self.properties.pop_front() {
+            (
+                Consuming::Properties,
+                Leftover::Property(property.key.into_string()),
+            )
+        } else if let Some(name) = child {
+            (Consuming::Children, Leftover::Child(name))
+        } else {
+            return Ok(());
+        };
+
+        Err(match kind == consuming {
+            true => Error::UnfinishedNode { leftover },
+            false => Error::IncompatibleContent { leftover },
+        })
+    }
+}
+
+/// Type providing sequence access to the arguments of a node
+struct ArgumentsSeqAccess<'i, 'a> {
+    node: BufferedNode<'i, 'a>,
+    options: Options,
+}
+
+impl<'de> de::SeqAccess<'de> for ArgumentsSeqAccess<'de, '_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.node
+            .next_argument()?
+            .map(|argument| seed.deserialize(ValueDeserializer::checked(argument, self.options)?))
+            .transpose()
+    }
+}
+
+/// MapAccess over the properties of a node. If the struct being deserialized
+/// has a `$kaydle::rest` field, properties that don't match any other field
+/// are collected and yielded as a single `$kaydle::rest` entry at the end.
+struct PropertiesMapAccess<'i, 'a> {
+    node: BufferedNode<'i, 'a>,
+    value: Option<PropertyValue<'i>>,
+    fields: &'static [&'static str],
+    rest: Option<Vec<Property<'i>>>,
+    options: Options,
+}
+
+/// The value associated with the most recently returned key in a
+/// [`PropertiesMapAccess`]
+enum PropertyValue<'i> {
+    Value {
+        key: KdlString<'i>,
+        value: AnnotatedValue<'i>,
+    },
+    Rest(Vec<Property<'i>>),
+}
+
+impl<'i, 'a> PropertiesMapAccess<'i, 'a> {
+    /// Create a new MapAccess over the properties of `node`. If duplicate
+    /// properties are resolved (see [`Options::duplicate_properties`]), the
+    /// node is read in full first.
+    fn new(
+        mut node: BufferedNode<'i, 'a>,
+        fields: &'static [&'static str],
+        options: Options,
+    ) -> Result<Self, Error> {
+        node.resolve_duplicates(options.duplicate_properties)?;
+
+        Ok(Self {
+            node,
+            value: None,
+            fields,
+            rest: fields.contains(&magics::REST).then(Vec::new),
+            options,
+        })
+    }
+
+    /// Finish the node after the map was deserialized, and make sure it was
+    /// fully consumed.
+    fn finish(self) -> Result<(), Error> {
+        let pending = match self.value {
+            Some(PropertyValue::Value { key, .. }) => Some(key.into_string()),
+            Some(PropertyValue::Rest(..)) => Some(magics::REST.to_owned()),
+            None => None,
+        };
+
+        self.node.finish(Consuming::Properties)?;
+
+        match pending {
+            None => Ok(()),
+            Some(key) => Err(Error::UnfinishedNode {
+                leftover: Leftover::Property(key),
+            }),
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for PropertiesMapAccess<'de, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        while let Some(property) = self.node.next_property()? {
+            match self.rest {
+                Some(ref mut rest) if !self.fields.iter().any(|&field| property.key == field) => {
+                    rest.push(property)
+                }
+                _ => {
+                    self.value = Some(PropertyValue::Value {
+                        key: property.key.clone(),
+                        value: property.value,
+                    });
+
+                    return seed
+                        .deserialize(KeyDeserializer::new(property.key))
+                        .map(Some);
+                }
+            }
+        }
+
+        match self.rest.take() {
+            None => Ok(None),
+            Some(rest) => {
+                self.value = Some(PropertyValue::Rest(rest));
+                seed.deserialize(BorrowedStrDeserializer::new(magics::REST))
+                    .map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(PropertyValue::Value { value, .. }) => {
+                seed.deserialize(ValueDeserializer::checked(value, self.options)?)
+            }
+            Some(PropertyValue::Rest(rest)) => seed.deserialize(MapAccessDeserializer::new(
+                BufferedPropertiesMapAccess::new(rest.into_iter(), self.options),
+            )),
+            None => panic!("called next_value_seed out of order"),
+        }
+    }
+}
+
+/// MapAccess over both the properties and the children of a node, used when
+/// they're merged (see [`PropertiesAndChildren::Merge`]). Every property is
+/// yielded before the children.
+#[derive(new)]
+struct MergedMapAccess<'i, 'p, 'c> {
+    properties: PropertiesMapAccess<'i, 'p>,
+    children: node_list::MapAccess<'i, 'c, Children<'i, 'p>>,
+
+    /// True once the properties have run out
+    #[new(default)]
+    in_children: bool,
+}
+
+impl<'de> de::MapAccess<'de> for MergedMapAccess<'de, '_, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if !self.in_children {
+            // The seed can only be used once, so check for another property
+            // before using it.
+            if let Some(property) = self.properties.node.next_property()? {
+                self.properties.node.properties.push_front(property);
+                return self.properties.next_key_seed(seed);
+            }
+
+            self.in_children = true;
+        }
+
+        self.children.next_key_seed(seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.in_children {
+            false => self.properties.next_value_seed(seed),
+            true => self.children.next_value_seed(seed),
+        }
+    }
+}
+
+/// SeqAccess over a set of buffered arguments, used for
+/// `$kaydle::arguments`
+#[derive(new)]
+struct BufferedArgumentsSeqAccess<I> {
+    arguments: I,
+    options: Options,
+}
+
+impl<'de, I> de::SeqAccess<'de> for BufferedArgumentsSeqAccess<I>
+where
+    I: ExactSizeIterator<Item = AnnotatedValue<'de>>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.arguments
+            .next()
+            .map(|argument| seed.deserialize(ValueDeserializer::checked(argument, self.options)?))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.arguments.len())
+    }
+}
+
+/// MapAccess over a set of buffered properties, used for
+/// `$kaydle::properties` and `$kaydle::rest`
+#[derive(new)]
+struct BufferedPropertiesMapAccess<'i, I> {
+    properties: I,
+    options: Options,
+
+    #[new(default)]
+    value: Option<AnnotatedValue<'i>>,
+}
+
+impl<'de, I> de::MapAccess<'de> for BufferedPropertiesMapAccess<'de, I>
+where
+    I: ExactSizeIterator<Item = Property<'de>>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        self.properties
+            .next()
+            .map(|property| {
+                self.value = Some(property.value);
+                seed.deserialize(KeyDeserializer::new(property.key))
             })
+            .transpose()
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .expect("called next_value_seed out of order");
+
+        seed.deserialize(ValueDeserializer::checked(value, self.options)?)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.properties.len())
+    }
+}
+
+/// The source of the ordinary (non-magic) fields of a struct that uses
+/// `$kaydle::arguments`, `$kaydle::properties`, or `$kaydle::children`
+enum FieldsAccess<'i, 'p, 'c> {
+    Properties(PropertiesMapAccess<'i, 'p>),
+    Children(node_list::MapAccess<'i, 'c, Children<'i, 'p>>),
+    Merged(Box<MergedMapAccess<'i, 'p, 'c>>),
+}
+
+/// The value associated with the most recently returned key in a
+/// [`MagicMapAccess`]
+enum MagicValue<'i, 'p> {
+    Arguments(VecDeque<AnnotatedValue<'i>>),
+    Properties(VecDeque<Property<'i>>),
+    Children(Option<Children<'i, 'p>>),
+    Field,
+}
+
+/// MapAccess for a struct that uses `$kaydle::arguments`,
+/// `$kaydle::properties`, or `$kaydle::children`. The magic entries are
+/// yielded first, followed by the ordinary fields of the struct, which come
+/// from either the properties or the children of the node (whichever wasn't
+/// collected by a magic).
+struct MagicMapAccess<'i, 'p, 'c> {
+    arguments: Option<VecDeque<AnnotatedValue<'i>>>,
+    properties: Option<VecDeque<Property<'i>>>,
+    children: Option<Option<Children<'i, 'p>>>,
+    fields: FieldsAccess<'i, 'p, 'c>,
+    value: Option<MagicValue<'i, 'p>>,
+    options: Options,
+}
+
+impl<'de> de::MapAccess<'de> for MagicMapAccess<'de, '_, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let (magic, value) = if let Some(arguments) = self.arguments.take() {
+            (magics::ARGUMENTS, MagicValue::Arguments(arguments))
+        } else if let Some(properties) = self.properties.take() {
+            (magics::PROPERTIES, MagicValue::Properties(properties))
+        } else if let Some(children) = self.children.take() {
+            (magics::CHILDREN, MagicValue::Children(children))
+        } else {
+            let key = match self.fields {
+                FieldsAccess::Properties(ref mut access) => access.next_key_seed(seed)?,
+                FieldsAccess::Children(ref mut access) => access.next_key_seed(seed)?,
+                FieldsAccess::Merged(ref mut access) => access.next_key_seed(seed)?,
+            };
+
+            if key.is_some() {
+                self.value = Some(MagicValue::Field);
+            }
+
+            return Ok(key);
+        };
+
+        self.value = Some(value);
+        seed.deserialize(BorrowedStrDeserializer::new(magic))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self
+            .value
+            .take()
+            .expect("called next_value_seed out of order")
+        {
+            MagicValue::Arguments(arguments) => seed.deserialize(SeqAccessDeserializer::new(
+                BufferedArgumentsSeqAccess::new(arguments.into_iter(), self.options),
+            )),
+            MagicValue::Properties(properties) => seed.deserialize(MapAccessDeserializer::new(
+                BufferedPropertiesMapAccess::new(properties.into_iter(), self.options),
+            )),
+            // An empty set of children is the same as no children
+            MagicValue::Children(Some(children)) if node_list::is_empty(&children)? => {
+                children.drain()?;
+                seed.deserialize(util::EmptyDeserializer::new())
+            }
+            MagicValue::Children(Some(children)) => seed.deserialize(
+                node_list::Deserializer::from_list(children).with_options(self.options),
+            ),
+            MagicValue::Children(None) => seed.deserialize(util::EmptyDeserializer::new()),
+            MagicValue::Field => match self.fields {
+                FieldsAccess::Properties(ref mut access) => access.next_value_seed(seed),
+                FieldsAccess::Children(ref mut access) => access.next_value_seed(seed),
+                FieldsAccess::Merged(ref mut access) => access.next_value_seed(seed),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use kaydle_primitives::{number::KdlNumber, value::KdlValue};
+    use serde::Deserialize;
+
+    use crate::serde::de::{
+        from_str, DuplicateProperties, Error, Leftover, Options, PropertiesAndChildren,
+    };
+
+    /// A self-describing document tree, like `serde_json::Value`
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Dom {
+        Null,
+        Bool(bool),
+        Int(i64),
+        String(String),
+        List(Vec<Dom>),
+        Map(BTreeMap<String, Dom>),
+    }
+
+    #[test]
+    fn any_shape_from_content() {
+        let document: BTreeMap<String, Dom> = from_str(
+            r#"
+            empty
+            single 1
+            several "a" true
+            props x=1 y="z"
+            children {
+                a 1
+                b {
+                    c null
+                }
+            }
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(document["empty"], Dom::Null);
+        assert_eq!(document["single"], Dom::Int(1));
+        assert_eq!(
+            document["several"],
+            Dom::List(vec![Dom::String("a".to_owned()), Dom::Bool(true)])
+        );
+        assert_eq!(
+            document["props"],
+            Dom::Map(BTreeMap::from([
+                ("x".to_owned(), Dom::Int(1)),
+                ("y".to_owned(), Dom::String("z".to_owned())),
+            ]))
+        );
+        assert_eq!(
+            document["children"],
+            Dom::Map(BTreeMap::from([
+                ("a".to_owned(), Dom::Int(1)),
+                (
+                    "b".to_owned(),
+                    Dom::Map(BTreeMap::from([("c".to_owned(), Dom::Null)]))
+                ),
+            ]))
+        );
+
+        from_str::<BTreeMap<String, Dom>>("mixed 1 x=2").expect_err("arguments and properties");
+        from_str::<BTreeMap<String, Dom>>("mixed 1 {\n  x 2\n}")
+            .expect_err("arguments and children");
+    }
+
+    #[test]
+    fn untagged_enum_variants() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(untagged)]
+        enum Listener {
+            Struct { port: u16 },
+            Pair(u16, u16),
+            Port(u16),
+        }
+
+        let document: BTreeMap<String, Listener> =
+            from_str("a port=80; b 53 54; c 443").expect("failed to deserialize");
+
+        assert_eq!(document["a"], Listener::Struct { port: 80 });
+        assert_eq!(document["b"], Listener::Pair(53, 54));
+        assert_eq!(document["c"], Listener::Port(443));
+
+        let err = from_str::<BTreeMap<String, Listener>>(r#"d "x""#).expect_err("no variant");
+        assert!(matches!(err, Error::Custom(..)));
+    }
+
+    #[test]
+    fn nested_rest() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Plugin {
+            name: String,
+
+            #[serde(rename = "$kaydle::rest")]
+            rest: BTreeMap<String, Dom>,
+        }
+
+        let plugin: Plugin = from_str(
+            r#"
+            name "auth"
+            t {
+                x 1
+            }
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(plugin.name, "auth");
+        assert_eq!(
+            plugin.rest,
+            BTreeMap::from([(
+                "t".to_owned(),
+                Dom::Map(BTreeMap::from([("x".to_owned(), Dom::Int(1))]))
+            )])
+        );
+    }
+
+    #[test]
+    fn duplicate_properties() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Magic {
+            #[serde(rename = "$kaydle::properties")]
+            properties: BTreeMap<String, i32>,
+        }
+
+        let input = "point x=1 y=2 x=3";
+
+        let err = from_str::<BTreeMap<String, Point>>(input).expect_err("duplicate field");
+        assert!(matches!(err, Error::Custom(..)));
+
+        let last = Options::new().duplicate_properties(DuplicateProperties::Last);
+        let points: BTreeMap<String, Point> = last.from_str(input).expect("last wins");
+        assert_eq!(points["point"], Point { x: 3, y: 2 });
+
+        let magic: BTreeMap<String, Magic> = last.from_str(input).expect("last wins");
+        assert_eq!(
+            magic["point"].properties,
+            BTreeMap::from([("x".to_owned(), 3), ("y".to_owned(), 2)])
+        );
+
+        let reject = Options::new().duplicate_properties(DuplicateProperties::Reject);
+        for err in [
+            reject
+                .from_str::<BTreeMap<String, Point>>(input)
+                .expect_err("rejected"),
+            reject
+                .from_str::<BTreeMap<String, Magic>>(input)
+                .expect_err("rejected"),
+            reject
+                .from_str::<BTreeMap<String, Dom>>("point x=1 x=1 {\n  child\n}\nnext")
+                .expect_err("rejected"),
+        ] {
+            assert!(
+                matches!(err, Error::DuplicateProperty { ref key } if key == "x"),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn properties_and_children() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            port: u16,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Tagged {
+            #[serde(rename = "$kaydle::arguments")]
+            tags: Vec<String>,
+            host: String,
+            port: u16,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Rest {
+            host: String,
+
+            #[serde(rename = "$kaydle::rest")]
+            rest: BTreeMap<String, Dom>,
+        }
+
+        let input = "server host=\"a\" {\n  port 80\n}";
+
+        from_str::<BTreeMap<String, Server>>(input).expect_err("exclusive");
+
+        let err = from_str::<BTreeMap<String, Tagged>>("server host=\"a\" {\n  port 80\n}")
+            .expect_err("exclusive");
+        assert!(
+            matches!(
+                err,
+                Error::IncompatibleContent { leftover: Leftover::Child(ref name) } if name == "port"
+            ),
+            "{err:?}"
+        );
+
+        let merge = Options::new().properties_and_children(PropertiesAndChildren::Merge);
+
+        let servers: BTreeMap<String, Server> = merge.from_str(input).expect("merged");
+        assert_eq!(
+            servers["server"],
+            Server {
+                host: "a".to_owned(),
+                port: 80
+            }
+        );
+
+        let tagged: BTreeMap<String, Tagged> = merge
+            .from_str("server \"x\" host=\"a\" {\n  port 80\n}")
+            .expect("merged");
+        assert_eq!(
+            tagged["server"],
+            Tagged {
+                tags: vec!["x".to_owned()],
+                host: "a".to_owned(),
+                port: 80
+            }
+        );
+
+        let dom: BTreeMap<String, Dom> = merge.from_str(input).expect("merged");
+        assert_eq!(
+            dom["server"],
+            Dom::Map(BTreeMap::from([
+                ("host".to_owned(), Dom::String("a".to_owned())),
+                ("port".to_owned(), Dom::Int(80)),
+            ]))
+        );
+
+        // A key used by both is a duplicate field
+        merge
+            .from_str::<BTreeMap<String, Server>>("server host=\"a\" port=80 {\n  port 81\n}")
+            .expect_err("duplicate field");
+
+        // Structs with a `$kaydle::rest` field are never merged
+        let err = merge
+            .from_str::<BTreeMap<String, Rest>>(input)
+            .expect_err("rest");
+        assert!(matches!(err, Error::IncompatibleContent { .. }), "{err:?}");
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    enum Listener {
+        #[serde(rename = "tcp")]
+        Tcp { port: u16 },
+
+        #[serde(rename = "udp")]
+        Udp(u16, u16),
+    }
+
+    #[test]
+    fn annotated_anonymous_node_enum() {
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Document {
+            main: Listener,
+            dns: Listener,
+        }
+
+        let document: Document = from_str(
+            r#"
+            (tcp)main port=8080
+            (udp)dns 53 54
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document,
+            Document {
+                main: Listener::Tcp { port: 8080 },
+                dns: Listener::Udp(53, 54),
+            }
+        );
+    }
+
+    #[test]
+    fn anonymous_node_enum_from_argument() {
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Document {
+            dns: Listener,
+        }
+
+        let document: Document = from_str(r#"dns "udp" 53 54"#).expect("failed to deserialize");
+        assert_eq!(document.dns, Listener::Udp(53, 54));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    #[serde(rename = "feature")]
+    struct Feature {
+        #[serde(rename = "$kaydle::annotation")]
+        annotation: Option<String>,
+        name: String,
+    }
+
+    #[test]
+    fn node_annotation_magic_named() {
+        let features: Vec<Feature> = from_str(
+            r#"
+            (deprecated)feature "x"
+            feature "y"
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            features,
+            [
+                Feature {
+                    annotation: Some("deprecated".to_owned()),
+                    name: "x".to_owned(),
+                },
+                Feature {
+                    annotation: None,
+                    name: "y".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn node_annotation_magic_children() {
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Listener {
+            #[serde(rename = "$kaydle::annotation")]
+            protocol: String,
+            host: String,
+            port: u16,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Document {
+            features: HashMap<String, Feature>,
+            listener: Listener,
+        }
+
+        let document: Document = from_str(
+            r#"
+            features {
+                a "x"
+                (deprecated)b "y"
+            }
+            (tcp)listener host="localhost" port=80
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document,
+            Document {
+                features: HashMap::from([
+                    (
+                        "a".to_owned(),
+                        Feature {
+                            annotation: None,
+                            name: "x".to_owned()
+                        }
+                    ),
+                    (
+                        "b".to_owned(),
+                        Feature {
+                            annotation: Some("deprecated".to_owned()),
+                            name: "y".to_owned()
+                        }
+                    ),
+                ]),
+                listener: Listener {
+                    protocol: "tcp".to_owned(),
+                    host: "localhost".to_owned(),
+                    port: 80,
+                },
+            }
+        );
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Extensible<'a> {
+        name: String,
+
+        #[serde(rename = "$kaydle::rest", borrow)]
+        rest: HashMap<String, KdlValue<'a>>,
+    }
+
+    #[test]
+    fn rest_properties() {
+        let document: HashMap<String, Extensible<'_>> = from_str(
+            r#"
+            plugin name="auth" color="red" size=3
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        let plugin = &document["plugin"];
+        assert_eq!(plugin.name, "auth");
+        assert_eq!(plugin.rest.len(), 2);
+        assert!(matches!(plugin.rest["color"], KdlValue::String(ref s) if *s == "red"));
+        assert!(matches!(
+            plugin.rest["size"],
+            KdlValue::Number(KdlNumber::Unsigned(3))
+        ));
+    }
+
+    #[test]
+    fn rest_children() {
+        let plugin: Extensible<'_> = from_str(
+            r#"
+            color "red"
+            name "auth"
+            enabled true
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(plugin.name, "auth");
+        assert_eq!(plugin.rest.len(), 2);
+        assert!(matches!(plugin.rest["color"], KdlValue::String(ref s) if *s == "red"));
+        assert!(matches!(plugin.rest["enabled"], KdlValue::Bool(true)));
+    }
+
+    #[test]
+    fn rest_empty() {
+        let plugin: Extensible<'_> = from_str(r#"name "auth""#).expect("failed to deserialize");
+        assert_eq!(plugin.name, "auth");
+        assert!(plugin.rest.is_empty());
+
+        let document: HashMap<String, Extensible<'_>> =
+            from_str(r#"plugin name="auth""#).expect("failed to deserialize");
+        assert!(document["plugin"].rest.is_empty());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Exec {
+        #[serde(rename = "$kaydle::arguments")]
+        args: Vec<String>,
+        cwd: String,
+    }
+
+    #[test]
+    fn interleaved_arguments_magic() {
+        let document: HashMap<String, Exec> =
+            from_str(r#"exec "ls" cwd="/" "-la""#).expect("failed to deserialize");
+
+        assert_eq!(
+            document["exec"],
+            Exec {
+                args: vec!["ls".to_owned(), "-la".to_owned()],
+                cwd: "/".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn properties_and_children_magics() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Server {
+            #[serde(rename = "$kaydle::properties")]
+            properties: HashMap<String, String>,
+            name: String,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Config {
+            debug: bool,
+
+            #[serde(rename = "$kaydle::children")]
+            limits: HashMap<String, i32>,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Document {
+            server: Server,
+            config: Config,
+        }
+
+        let document: Document = from_str(
+            r#"
+            server host="localhost" {
+                name "alpha"
+            }
+            config debug=true {
+                connections 10
+                requests 100
+            }
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document,
+            Document {
+                server: Server {
+                    properties: HashMap::from([("host".to_owned(), "localhost".to_owned())]),
+                    name: "alpha".to_owned(),
+                },
+                config: Config {
+                    debug: true,
+                    limits: HashMap::from([
+                        ("connections".to_owned(), 10),
+                        ("requests".to_owned(), 100)
+                    ]),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn absent_children_magic() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Item {
+            #[serde(rename = "$kaydle::arguments")]
+            arguments: (i32, i32),
+
+            #[serde(rename = "$kaydle::children")]
+            children: Vec<Item>,
+        }
+
+        let document: HashMap<String, Item> = from_str("item 1 2").expect("failed to deserialize");
+
+        assert_eq!(
+            document["item"],
+            Item {
+                arguments: (1, 2),
+                children: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_magic() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Item {
+            #[serde(rename = "$kaydle::argument")]
+            argument: i32,
+        }
+
+        let error = from_str::<HashMap<String, Item>>("item 1")
+            .expect_err("unknown magics should be rejected");
+        assert!(
+            matches!(&error, Error::Custom(message) if message == "unknown kaydle magic \"$kaydle::argument\" on Item"),
+            "unexpected error: {error:?}"
+        );
+    }
+
+    #[test]
+    fn empty_children() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Item {
+            #[serde(rename = "$kaydle::children")]
+            children: Option<BTreeMap<String, i32>>,
+        }
+
+        // An empty set of children is the same as no children, everywhere
+        let document: HashMap<String, Point> =
+            from_str("point x=1 y=2 {}").expect("failed to deserialize");
+        assert_eq!(document["point"], Point { x: 1, y: 2 });
+
+        let document: HashMap<String, (i32, i32)> =
+            from_str("pair 1 2 {}").expect("failed to deserialize");
+        assert_eq!(document["pair"], (1, 2));
+
+        let document: BTreeMap<String, Dom> = from_str("empty {}").expect("failed to deserialize");
+        assert_eq!(document["empty"], Dom::Null);
+
+        let document: BTreeMap<String, Item> =
+            from_str("a {}\nb\nc {\n  x 1\n}").expect("failed to deserialize");
+        assert_eq!(document["a"], Item { children: None });
+        assert_eq!(document["b"], Item { children: None });
+        assert_eq!(
+            document["c"],
+            Item {
+                children: Some(BTreeMap::from([("x".to_owned(), 1)]))
+            }
+        );
+    }
+
+    #[test]
+    fn unfinished_arguments() {
+        let err = from_str::<HashMap<String, (i32, i32)>>("point 1 2 3")
+            .expect_err("third argument is unused");
+
+        assert_eq!(
+            err.to_string(),
+            "sequence or map deserializer didn't consume the whole node; argument 2 was left over"
+        );
+        assert!(matches!(
+            err,
+            Error::UnfinishedNode {
+                leftover: Leftover::Argument(2)
+            }
+        ));
+
+        let err = from_str::<HashMap<String, i32>>("value 1 2").expect_err("extra argument");
+        assert!(matches!(
+            err,
+            Error::UnfinishedNode {
+                leftover: Leftover::Argument(1)
+            }
+        ));
+    }
+
+    #[test]
+    fn incompatible_content() {
+        let err = from_str::<HashMap<String, Vec<i32>>>("values 1 a=2 3")
+            .expect_err("property can't be part of a sequence");
+        assert!(matches!(
+            err,
+            Error::IncompatibleContent {
+                leftover: Leftover::Property(ref key)
+            } if key == "a"
+        ));
+
+        let err = from_str::<HashMap<String, HashMap<String, i32>>>("point x=1 2 y=3")
+            .expect_err("argument can't be part of a map");
+        assert!(matches!(
+            err,
+            Error::IncompatibleContent {
+                leftover: Leftover::Argument(0)
+            }
+        ));
+
+        let err = from_str::<HashMap<String, i32>>("value 1 { child 2; }")
+            .expect_err("children can't be part of a primitive");
+        assert!(matches!(
+            err,
+            Error::IncompatibleContent {
+                leftover: Leftover::Child(ref name)
+            } if name == "child"
+        ));
+    }
+
+    #[test]
+    fn unfinished_children() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Point(i32, i32);
+
+        let err = from_str::<HashMap<String, (Point,)>>(
+            r#"
+            points {
+                Point 1 2
+                Point 3 4
+            }
+            "#,
+        )
+        .expect_err("second point is unused");
+
+        assert!(matches!(
+            err,
+            Error::UnfinishedNode {
+                leftover: Leftover::Child(ref name)
+            } if name == "Point"
+        ));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Port(u16);
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Secret(String);
+
+    #[test]
+    fn newtype_structs() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Server {
+            port: Port,
+            secret: Secret,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Document {
+            server: Server,
+            admin: Server,
+            ports: Vec<Port>,
+        }
+
+        let document: Document = from_str(
+            r#"
+            server {
+                port 8080
+                secret "hunter2"
+            }
+            admin port=9090 secret="swordfish"
+            ports 80 443
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document,
+            Document {
+                server: Server {
+                    port: Port(8080),
+                    secret: Secret("hunter2".to_owned()),
+                },
+                admin: Server {
+                    port: Port(9090),
+                    secret: Secret("swordfish".to_owned()),
+                },
+                ports: vec![Port(80), Port(443)],
+            }
+        );
     }
 }
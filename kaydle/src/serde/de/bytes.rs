@@ -0,0 +1,170 @@
+//! Decoding of byte buffers, from `(base64)` or `(hex)` strings, or from the
+//! numeric arguments of a node.
+
+use kaydle_primitives::{
+    annotation::AnnotatedValue, number::KdlNumber, reserved::decode_base64, value::KdlValue,
+};
+
+use super::Error;
+
+/// Decode a value as a byte buffer, if it's a string annotated with
+/// `(base64)` or `(hex)`. Returns `None` for any other value, which should be
+/// deserialized normally.
+pub fn decode(value: &AnnotatedValue<'_>) -> Result<Option<Vec<u8>>, Error> {
+    let (annotation, s) = match (&value.annotation, &value.item) {
+        (Some(annotation), KdlValue::String(s)) => (annotation.as_str(), s.as_str()),
+        _ => return Ok(None),
+    };
+
+    let (encoding, bytes) = match annotation {
+        "base64" => ("base64", decode_base64(s)),
+        "hex" => ("hex", decode_hex(s)),
+        _ => return Ok(None),
+    };
+
+    bytes
+        .map(Some)
+        .map_err(|reason| Error::InvalidEncodedBytes { encoding, reason })
+}
+
+/// Convert the argument at `index` of a node to a byte, for byte buffers
+/// made from a list of numeric arguments
+pub fn from_argument(index: usize, value: &KdlValue<'_>) -> Result<u8, Error> {
+    match *value {
+        KdlValue::Number(KdlNumber::Unsigned(byte)) => u8::try_from(byte).ok(),
+        KdlValue::Number(KdlNumber::Signed(byte)) => u8::try_from(byte).ok(),
+        _ => None,
+    }
+    .ok_or(Error::InvalidByte { index })
+}
+
+/// Decode hexadecimal, with two digits per byte. Whitespace is ignored.
+fn decode_hex(s: &str) -> Result<Vec<u8>, &'static str> {
+    let digits = s
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| c.to_digit(16).ok_or("invalid character"))
+        .collect::<Result<Vec<u32>, _>>()?;
+
+    match digits.len() % 2 {
+        0 => Ok(digits
+            .chunks(2)
+            .map(|pair| (pair[0] << 4 | pair[1]) as u8)
+            .collect()),
+        _ => Err("odd number of digits"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::serde::de::{from_str, Error, Options};
+
+    /// A byte buffer that only deserializes from bytes, like
+    /// `serde_bytes::ByteBuf`
+    #[derive(Debug, PartialEq, Eq)]
+    struct Bytes(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for Bytes {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl<'de> serde::de::Visitor<'de> for Visitor {
+                type Value = Bytes;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("bytes")
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                    Ok(Bytes(v))
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                    Ok(Bytes(v.to_owned()))
+                }
+            }
+
+            deserializer.deserialize_byte_buf(Visitor)
+        }
+    }
+
+    #[test]
+    fn byte_buffers() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Document {
+            base64: Bytes,
+            hex: Bytes,
+            numbers: Bytes,
+            empty: Bytes,
+            values: Vec<Bytes>,
+        }
+
+        let input = r#"
+            base64 (base64)"3q2+\n    7w=="
+            hex (hex)"DEAD beef"
+            numbers 0xde 0xad 190 0o357
+            empty
+            values (base64)"AQ" (hex)"0203" (base64)"3q2+7w"
+            "#;
+
+        let document: Document = from_str(input).expect("failed to deserialize");
+
+        let expected = Bytes(vec![0xde, 0xad, 0xbe, 0xef]);
+
+        assert_eq!(document.base64, expected);
+        assert_eq!(document.hex, expected);
+        assert_eq!(document.numbers, expected);
+        assert_eq!(document.empty, Bytes(Vec::new()));
+        assert_eq!(
+            document.values,
+            [Bytes(vec![1]), Bytes(vec![2, 3]), expected]
+        );
+
+        // Validating annotations accepts the same base64 that's decoded
+        let strict: Document = Options::strict()
+            .from_str(input)
+            .expect("failed to deserialize strictly");
+        assert_eq!(strict, document);
+    }
+
+    #[test]
+    fn invalid_byte_buffers() {
+        #[derive(Deserialize, Debug)]
+        struct Document {
+            #[allow(dead_code)]
+            data: Bytes,
+        }
+
+        let err = from_str::<Document>("data 1 2 256").expect_err("256 isn't a byte");
+        assert!(matches!(err, Error::InvalidByte { index: 2 }));
+
+        let err = from_str::<Document>("data 1 -1").expect_err("-1 isn't a byte");
+        assert!(matches!(err, Error::InvalidByte { index: 1 }));
+
+        let err = from_str::<Document>(r#"data (hex)"abc""#).expect_err("odd digits");
+        assert!(matches!(
+            err,
+            Error::InvalidEncodedBytes {
+                encoding: "hex",
+                ..
+            }
+        ));
+
+        let err = from_str::<Document>(r#"data (base64)"A===""#).expect_err("truncated");
+        assert!(matches!(
+            err,
+            Error::InvalidEncodedBytes {
+                encoding: "base64",
+                ..
+            }
+        ));
+
+        let err = from_str::<Document>(r#"data 1 "two""#).expect_err("not a number");
+        assert!(matches!(err, Error::InvalidByte { index: 1 }));
+    }
+}
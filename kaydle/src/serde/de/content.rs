@@ -0,0 +1,39 @@
+/// What happens when a node has more than one property with the same key
+/// (see [`Options::duplicate_properties`][super::Options::duplicate_properties]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DuplicateProperties {
+    /// Every property is passed to the type being deserialized, in order.
+    /// Structs derived with serde reject the repeated key as a duplicate
+    /// field, and most maps keep the last value. This is the default.
+    #[default]
+    Keep,
+
+    /// Only the last (rightmost) property with each key is used, which is
+    /// how the KDL spec says duplicate properties should be resolved.
+    Last,
+
+    /// Deserialization fails with
+    /// [`Error::DuplicateProperty`][super::Error::DuplicateProperty].
+    Reject,
+}
+
+/// Where the fields of a struct (or the entries of a map) come from when an
+/// anonymous node has both properties and children (see
+/// [`Options::properties_and_children`][super::Options::properties_and_children]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PropertiesAndChildren {
+    /// The fields come from either the properties or the children, but not
+    /// both, and a node with both fails with
+    /// [`Error::IncompatibleContent`][super::Error::IncompatibleContent]. An
+    /// empty children block is allowed alongside properties. This is the
+    /// default.
+    #[default]
+    Exclusive,
+
+    /// The properties and children are merged into one map, with the
+    /// properties first. A key that's used by both a property and a child is
+    /// passed to the type twice. Structs with a `$kaydle::rest` field are
+    /// never merged, since the leftovers of both would need to be collected
+    /// into one field.
+    Merge,
+}
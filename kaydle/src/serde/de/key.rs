@@ -0,0 +1,181 @@
+use std::{marker::PhantomData, str::FromStr};
+
+use derive_new::new;
+use kaydle_primitives::string::KdlString;
+use serde::{de, forward_to_deserialize_any};
+
+use super::string::Deserializer as StringDeserializer;
+
+/// A deserializer for map keys, which are node names or property keys. Like
+/// a [`StringDeserializer`], but keys can also be parsed into primitive
+/// types, the same way `serde_json` handles object keys. This allows types
+/// like `HashMap<u16, String>` to be deserialized from nodes like `"80"
+/// "http"`.
+#[derive(Debug, new)]
+pub struct Deserializer<'i, E> {
+    key: KdlString<'i>,
+    error: PhantomData<E>,
+}
+
+impl<'i, E: de::Error> Deserializer<'i, E> {
+    /// Parse the key as some primitive type, and pass it to the visitor with
+    /// `visit`.
+    fn parse<'de, T, V>(
+        self,
+        visitor: V,
+        visit: impl FnOnce(V, T) -> Result<V::Value, E>,
+    ) -> Result<V::Value, E>
+    where
+        T: FromStr,
+        V: de::Visitor<'de>,
+    {
+        match self.key.as_str().parse() {
+            Ok(value) => visit(visitor, value),
+            Err(_) => Err(E::invalid_value(
+                de::Unexpected::Str(self.key.as_str()),
+                &visitor,
+            )),
+        }
+    }
+}
+
+macro_rules! parse_key {
+    ($($deserialize:ident => $visit:ident,)*) => {$(
+        #[inline]
+        fn $deserialize<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            self.parse(visitor, V::$visit)
+        }
+    )*};
+}
+
+impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
+    type Error = E;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.key.visit_to(visitor)
+    }
+
+    parse_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        StringDeserializer::new(self.key).deserialize_enum(name, variants, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use crate::serde::de::from_str;
+
+    #[derive(Deserialize, Debug, PartialEq, Eq, Hash)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Info,
+    }
+
+    #[test]
+    fn non_string_keys() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Document {
+            ports: HashMap<u16, String>,
+            flags: HashMap<bool, i32>,
+            letters: HashMap<char, i32>,
+            levels: HashMap<Level, f64>,
+
+            #[serde(rename = "$kaydle::keyed::server")]
+            servers: HashMap<u8, String>,
+        }
+
+        let document: Document = from_str(
+            r#"
+            ports {
+                "80" "http"
+                "443" "https"
+            }
+            flags "true"=1 "false"=0
+            letters a=1 b=2
+            levels debug=0.5
+            server "1" "alpha"
+            server 2 "beta"
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document,
+            Document {
+                ports: HashMap::from([(80, "http".to_owned()), (443, "https".to_owned())]),
+                flags: HashMap::from([(true, 1), (false, 0)]),
+                letters: HashMap::from([('a', 1), ('b', 2)]),
+                levels: HashMap::from([(Level::Debug, 0.5)]),
+                servers: HashMap::from([(1, "alpha".to_owned()), (2, "beta".to_owned())]),
+            }
+        );
+
+        let err = from_str::<HashMap<u8, i32>>("x 1").expect_err("x isn't a number");
+        assert_eq!(
+            err.to_string(),
+            "error from Deserialize type: invalid value: string \"x\", expected u8"
+        );
+    }
+}
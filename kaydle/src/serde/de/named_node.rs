@@ -7,17 +7,18 @@ use serde::de;
 
 use super::{
     anonymous_node::Deserializer as AnonymousNodeDeserializer,
-    string::Deserializer as StringDeserializer, Error,
+    string::Deserializer as StringDeserializer, Error, Options,
 };
 
 #[derive(Debug)]
 pub struct Deserializer<'i, 'p> {
     node: Annotated<'i, Node<'i, 'p, KdlString<'i>>>,
+    options: Options,
 }
 
 impl<'i, 'p> Deserializer<'i, 'p> {
-    pub fn new(node: Annotated<'i, Node<'i, 'p, KdlString<'i>>>) -> Self {
-        Self { node }
+    pub fn new(node: Annotated<'i, Node<'i, 'p, KdlString<'i>>>, options: Options) -> Self {
+        Self { node, options }
     }
 
     /// Extract the name from `self.node` and return the rest of it as an
@@ -25,13 +26,32 @@ impl<'i, 'p> Deserializer<'i, 'p> {
     fn into_parts(self) -> (KdlString<'i>, AnonymousNodeDeserializer<'i, 'p>) {
         (
             self.node.item.name,
-            AnonymousNodeDeserializer::new(GenericAnnotated {
-                annotation: self.node.annotation,
-                item: self.node.item.content,
-            }),
+            AnonymousNodeDeserializer::new(
+                GenericAnnotated {
+                    annotation: self.node.annotation,
+                    item: self.node.item.content,
+                },
+                self.options,
+            ),
         )
     }
 
+    /// Check that the node name matches the name of the type being
+    /// deserialized, according to the [`Options`], and return the rest of the
+    /// node as an anonymous deserializer
+    fn into_typed(
+        self,
+        type_name: &'static str,
+    ) -> Result<AnonymousNodeDeserializer<'i, 'p>, Error> {
+        let options = self.options;
+        let (node_name, node) = self.into_parts();
+
+        match options.wildcard_names.matches(node_name.as_str()) {
+            true => Ok(node),
+            false => options.check_type_name(node_name, type_name).map(|()| node),
+        }
+    }
+
     /// Treat this named node as an anonymous node if the node name is "-"
     fn become_anonymous(self) -> Result<AnonymousNodeDeserializer<'i, 'p>, Error> {
         let (name, node) = self.into_parts();
@@ -88,15 +108,7 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     where
         V: de::Visitor<'de>,
     {
-        let (node_name, node) = self.into_parts();
-
-        match node_name == name {
-            true => node.deserialize_unit(visitor),
-            false => Err(Error::TypeNameMismatch {
-                node_name: node_name.into_string(),
-                type_name: name,
-            }),
-        }
+        self.into_typed(name)?.deserialize_unit(visitor)
     }
 
     fn deserialize_newtype_struct<V>(
@@ -107,17 +119,11 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     where
         V: de::Visitor<'de>,
     {
-        let (node_name, node) = self.into_parts();
+        let node = self.into_typed(name)?;
 
-        // TODO: completion check here; need to ensure that node has
-        // been drained.
-        match node_name == name {
-            true => visitor.visit_newtype_struct(node),
-            false => Err(Error::TypeNameMismatch {
-                node_name: node_name.into_string(),
-                type_name: name,
-            }),
-        }
+        // The anonymous node deserializer checks that the node is fully
+        // consumed, no matter how the inner type deserializes it.
+        visitor.visit_newtype_struct(node)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -129,15 +135,7 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     where
         V: de::Visitor<'de>,
     {
-        let (node_name, node) = self.into_parts();
-
-        match node_name == name {
-            true => node.deserialize_tuple(len, visitor),
-            false => Err(Error::TypeNameMismatch {
-                node_name: node_name.into_string(),
-                type_name: name,
-            }),
-        }
+        self.into_typed(name)?.deserialize_tuple(len, visitor)
     }
 
     fn deserialize_struct<V>(
@@ -149,33 +147,40 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
     where
         V: de::Visitor<'de>,
     {
-        let (node_name, node) = self.into_parts();
+        let node = self.into_typed(name)?;
 
         // TODO: various magics, and in particular a node name magic
         // TODO: add a deserialize_records method to Anonymous deserializer,
         // comparable to tuple vs tuple struct
         // TODO: Find an efficient way to strip `kaydle::name` from the fields
         // slice.
-        match node_name == name {
-            true => node.deserialize_struct(name, fields, visitor),
-            false => Err(Error::TypeNameMismatch {
-                node_name: node_name.into_string(),
-                type_name: name,
-            }),
-        }
+        node.deserialize_struct(name, fields, visitor)
     }
 
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
+        name: &'static str,
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        // TODO: check that self is drained afterwards. Probably want a
-        // different type here.
+        // If the node is annotated, the annotation is the enum variant, so
+        // the node name is treated like the name of any other named type.
+        if self.node.annotation.is_some() {
+            let options = self.options;
+            let (node_name, node) = self.into_parts();
+
+            if node_name != "-" {
+                options.check_type_name(node_name, name)?;
+            }
+
+            return node.deserialize_enum(name, variants, visitor);
+        }
+
+        // The variant content is an anonymous node deserializer, which checks
+        // that the node is fully consumed.
         visitor.visit_enum(self)
     }
 
@@ -202,3 +207,77 @@ impl<'de, 'p> de::EnumAccess<'de> for Deserializer<'de, 'p> {
             .map(|variant| (variant, deserializer))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::serde::de::{from_str, Error};
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    enum Listener {
+        #[serde(rename = "tcp")]
+        Tcp { port: u16 },
+
+        #[serde(rename = "udp")]
+        Udp(u16, u16),
+    }
+
+    #[test]
+    fn annotated_named_node_enum() {
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Document {
+            listeners: Vec<Listener>,
+        }
+
+        let document: Document = from_str(
+            r#"
+            listeners {
+                (tcp)- port=80
+                (udp)- 53 54
+            }
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document.listeners,
+            [Listener::Tcp { port: 80 }, Listener::Udp(53, 54)]
+        );
+    }
+
+    #[test]
+    fn annotated_named_node_enum_type_name() {
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        #[serde(rename = "listener")]
+        enum Renamed {
+            #[serde(rename = "tcp")]
+            Tcp { port: u16 },
+        }
+
+        let listeners: Vec<Renamed> =
+            from_str("(tcp)listener port=80").expect("failed to deserialize");
+        assert_eq!(listeners, [Renamed::Tcp { port: 80 }]);
+
+        let err = from_str::<Vec<Renamed>>("(tcp)server port=80").expect_err("name mismatch");
+        assert!(matches!(err, Error::TypeNameMismatch { .. }));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Port(u16);
+
+    #[test]
+    fn named_newtype_struct() {
+        let ports: Vec<Port> = from_str("Port 80; Port 443").expect("failed to deserialize");
+        assert_eq!(ports, [Port(80), Port(443)]);
+
+        let err = from_str::<Vec<Port>>("Secret 80").expect_err("node name must match");
+        assert!(matches!(
+            err,
+            Error::TypeNameMismatch {
+                type_name: "Port",
+                ..
+            }
+        ));
+    }
+}
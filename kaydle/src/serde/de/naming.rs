@@ -0,0 +1,194 @@
+use std::borrow::Cow;
+
+/// How node names are matched against the names of the types they're
+/// deserialized into (see [`Options::name_matching`][super::Options::name_matching]).
+/// Whatever the policy, a node name that's exactly the same as the type name
+/// always matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NameMatching {
+    /// The node name must be exactly the same as the type name. This is the
+    /// default.
+    #[default]
+    Exact,
+
+    /// The node name must be the type name in kebab-case, so `HttpServer`
+    /// matches nodes called `http-server`.
+    KebabCase,
+
+    /// The node name must be the type name in snake_case, so `HttpServer`
+    /// matches nodes called `http_server`.
+    SnakeCase,
+
+    /// The node name must be the same as the type name, ignoring case, so
+    /// `HttpServer` matches nodes called `httpserver` or `HTTPSERVER`.
+    CaseInsensitive,
+}
+
+impl NameMatching {
+    /// The spelling of a type name that a node name is expected to have
+    /// under this policy. For [`CaseInsensitive`][Self::CaseInsensitive],
+    /// this is the type name itself.
+    pub fn expected_name(self, type_name: &str) -> Cow<'_, str> {
+        match self {
+            NameMatching::Exact | NameMatching::CaseInsensitive => Cow::Borrowed(type_name),
+            NameMatching::KebabCase => Cow::Owned(join_words(type_name, '-')),
+            NameMatching::SnakeCase => Cow::Owned(join_words(type_name, '_')),
+        }
+    }
+
+    /// Check if a node name matches a type name under this policy
+    pub fn matches(self, node_name: &str, type_name: &str) -> bool {
+        node_name == type_name
+            || match self {
+                NameMatching::Exact => false,
+                NameMatching::CaseInsensitive => {
+                    node_name.to_lowercase() == type_name.to_lowercase()
+                }
+                NameMatching::KebabCase | NameMatching::SnakeCase => {
+                    node_name == self.expected_name(type_name)
+                }
+            }
+    }
+}
+
+/// Which node names match any type, regardless of the [`NameMatching`]
+/// policy (see [`Options::wildcard_names`][super::Options::wildcard_names]).
+/// This only applies to structs, newtype structs, tuple structs, and unit
+/// structs; annotated enum nodes always accept `-`, and un-annotated enum
+/// nodes use their name as the variant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WildcardNames {
+    /// Node names must always match the type name. This is the default.
+    #[default]
+    None,
+
+    /// Nodes called `-` match any type
+    Dash,
+
+    /// Node names aren't checked at all
+    Any,
+}
+
+impl WildcardNames {
+    /// Check if a node name matches any type
+    pub fn matches(self, node_name: &str) -> bool {
+        match self {
+            WildcardNames::None => false,
+            WildcardNames::Dash => node_name == "-",
+            WildcardNames::Any => true,
+        }
+    }
+}
+
+/// Split a type name into lowercase words, and join them with `separator`.
+/// Words are separated by `_`, `-`, or a change in case, so `HTTPServer`,
+/// `HttpServer`, and `http_server` all become `http{separator}server`.
+fn join_words(name: &str, separator: char) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut joined = String::with_capacity(name.len() + 4);
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !joined.is_empty() && !joined.ends_with(separator) {
+                joined.push(separator);
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            // A new word starts at an uppercase letter after a lowercase
+            // letter or digit (`HttpServer`), or at the last uppercase
+            // letter of an acronym that's followed by a lowercase letter
+            // (`HTTPServer`).
+            let boundary = prev.is_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_uppercase() && next_is_lower);
+
+            if boundary && !joined.ends_with(separator) {
+                joined.push(separator);
+            }
+        }
+
+        joined.extend(c.to_lowercase());
+    }
+
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::{NameMatching, WildcardNames};
+    use crate::serde::de::{from_str, Error, Options};
+
+    #[test]
+    fn node_name_matching() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct HttpServer {
+            port: u16,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Port(u16);
+
+        let kebab = Options::new().name_matching(NameMatching::KebabCase);
+        let servers: Vec<HttpServer> = kebab
+            .from_str("http-server port=80; HttpServer port=81")
+            .expect("kebab-case names");
+        assert_eq!(servers, [HttpServer { port: 80 }, HttpServer { port: 81 }]);
+
+        let err = kebab
+            .from_str::<Vec<HttpServer>>("http_server port=80")
+            .expect_err("snake_case isn't kebab-case");
+        assert!(matches!(
+            err,
+            Error::TypeNameMismatch {
+                type_name: "HttpServer",
+                ref expected,
+                ..
+            } if expected == "http-server"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "attempted to deserialize a type called HttpServer from a node called \
+            http_server; expected a node called http-server"
+        );
+
+        let snake = Options::new().name_matching(NameMatching::SnakeCase);
+        let servers: Vec<HttpServer> = snake
+            .from_str("http_server port=80")
+            .expect("snake_case names");
+        assert_eq!(servers, [HttpServer { port: 80 }]);
+
+        let any_case = Options::new().name_matching(NameMatching::CaseInsensitive);
+        let ports: Vec<Port> = any_case.from_str("port 80; PORT 81").expect("any case");
+        assert_eq!(ports, [Port(80), Port(81)]);
+
+        from_str::<Vec<Port>>("port 80").expect_err("exact by default");
+
+        let dash = Options::new().wildcard_names(WildcardNames::Dash);
+        let ports: Vec<Port> = dash.from_str("- 80; Port 81").expect("dash wildcard");
+        assert_eq!(ports, [Port(80), Port(81)]);
+        dash.from_str::<Vec<Port>>("other 80")
+            .expect_err("only dash is a wildcard");
+
+        let any = Options::new().wildcard_names(WildcardNames::Any);
+        let ports: Vec<Port> = any.from_str("other 80; - 81").expect("any wildcard");
+        assert_eq!(ports, [Port(80), Port(81)]);
+
+        for (type_name, kebab, snake) in [
+            ("HttpServer", "http-server", "http_server"),
+            ("HTTPServer", "http-server", "http_server"),
+            ("http_server", "http-server", "http_server"),
+            ("Ipv4Addr", "ipv4-addr", "ipv4_addr"),
+            ("URL", "url", "url"),
+        ] {
+            assert_eq!(NameMatching::KebabCase.expected_name(type_name), kebab);
+            assert_eq!(NameMatching::SnakeCase.expected_name(type_name), snake);
+        }
+    }
+}
@@ -1,31 +1,87 @@
-use std::{char::CharTryFromError, marker::PhantomData, ptr::NonNull};
-
 use derive_new::new;
 use kaydle_primitives::{
-    annotation::Annotated,
-    node::{Document, DrainOutcome, NodeContent, NodeList},
-    string::StringBuilder,
+    annotation::{Annotated, RecognizedAnnotation, RecognizedAnnotationValue},
+    node::{Document, Node, NodeContent, NodeEvent, NodeList, RecognizedNode, Siblings},
+    property::RecognizedProperty,
+    string::KdlString,
+    value::KdlValue,
 };
-use nom::error::{FromExternalError, ParseError};
-use nom_supreme::{context::ContextError, tag::TagError};
 use serde::{de, forward_to_deserialize_any};
 
+use crate::serde::magics;
+
 use super::{
     anonymous_node::Deserializer as AnonymousNodeDeserializer,
-    named_node::Deserializer as NamedNodeDeserializer, string::Deserializer as StringDeserializer,
-    Error,
+    key::Deserializer as KeyDeserializer, named_node::Deserializer as NamedNodeDeserializer,
+    stream::StreamDeserializer, value::raw::Deserializer as RawValueDeserializer, Error, Options,
 };
 
+/// Drain the rest of a node list, returning the name of the first node that
+/// was drained (if any). Used to report unused nodes.
+pub fn drain_unused<'i>(mut list: impl NodeList<'i>) -> Result<Option<String>, Error> {
+    let node: Annotated<'i, Node<'i, '_, KdlString<'i>>> = match list.next_node()? {
+        None => return Ok(None),
+        Some(node) => node,
+    };
+
+    let name = node.item.name.into_string();
+    node.item.content.drain()?;
+    list.drain()?;
+
+    Ok(Some(name))
+}
+
+/// Check if there are no more nodes in a node list, without consuming any of
+/// them. Used to treat an empty set of children the same as absent children.
+pub fn is_empty<'i>(list: &impl NodeList<'i>) -> Result<bool, Error> {
+    let mut lookahead = list.siblings();
+    let node: Option<RecognizedAnnotation<RecognizedNode<'i, '_>>> = lookahead.next_node()?;
+    Ok(node.is_none())
+}
+
 /// Deserializer for a NodeList. Used for both documents and children.
 pub struct Deserializer<T> {
     list: T,
+    options: Options,
 }
 
 impl<'de> Deserializer<Document<'de>> {
     /// Create a new Deserializer that will Deserialize from a KDL document
     /// contained in a string.
+    ///
+    /// Unlike [`Options::from_str`], this doesn't scan the document for
+    /// exceeded [`Limits`][kaydle_primitives::limits::Limits] up front. Only
+    /// [`max_depth`][kaydle_primitives::limits::Limits::max_depth] is
+    /// enforced, as nested children are deserialized.
     pub fn new(document: Document<'de>) -> Self {
-        Self { list: document }
+        Self::from_list(document)
+    }
+
+    /// Turn this deserializer into an iterator that deserializes each
+    /// top-level node in the document as a separate `T`.
+    ///
+    /// Unlike [`StreamDeserializer::new`], this doesn't check each node
+    /// against the [`Limits`][kaydle_primitives::limits::Limits] before it's
+    /// deserialized; see [`new`][Self::new].
+    pub fn into_stream<T: de::Deserialize<'de>>(self) -> StreamDeserializer<'de, T> {
+        StreamDeserializer::from_document(self.list, self.options)
+    }
+}
+
+impl<'de, T: NodeList<'de>> Deserializer<T> {
+    /// Create a new Deserializer that will deserialize from an arbitrary node
+    /// list, such as the children of a node.
+    pub fn from_list(list: T) -> Self {
+        Self {
+            list,
+            options: Options::default(),
+        }
+    }
+
+    /// Use some custom [`Options`] for this deserializer, instead of the
+    /// defaults.
+    pub fn with_options(self, options: Options) -> Self {
+        Self { options, ..self }
     }
 }
 
@@ -41,7 +97,14 @@ impl<'de, T: NodeList<'de>> de::Deserializer<'de> for Deserializer<T> {
 
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct enum identifier
+        bytes byte_buf unit unit_struct newtype_struct enum identifier
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
     }
 
     fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
@@ -49,11 +112,11 @@ impl<'de, T: NodeList<'de>> de::Deserializer<'de> for Deserializer<T> {
         V: de::Visitor<'de>,
     {
         // TODO: Nested Errors
-        let value = visitor.visit_seq(SeqAccess::new(&mut self.list))?;
+        let value = visitor.visit_seq(SeqAccess::new(&mut self.list, self.options))?;
 
-        match self.list.drain()? {
-            DrainOutcome::Empty => Ok(value),
-            DrainOutcome::NotEmpty => Err(Error::UnusedNode),
+        match drain_unused(self.list)? {
+            None => Ok(value),
+            Some(name) => Err(Error::UnusedNode { name }),
         }
     }
 
@@ -76,28 +139,28 @@ impl<'de, T: NodeList<'de>> de::Deserializer<'de> for Deserializer<T> {
         self.deserialize_tuple(len, visitor)
     }
 
-    fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        let value = visitor.visit_map(MapAccess::new(&mut self.list))?;
-
-        match self.list.drain()? {
-            DrainOutcome::Empty => Ok(value),
-            DrainOutcome::NotEmpty => Err(Error::UnusedNode),
-        }
+        self.deserialize_struct("", &[], visitor)
     }
 
     fn deserialize_struct<V>(
-        self,
+        mut self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        let value = visitor.visit_map(MapAccess::new(&mut self.list, fields, self.options))?;
+
+        match drain_unused(self.list)? {
+            None => Ok(value),
+            Some(name) => Err(Error::UnusedNode { name }),
+        }
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -112,6 +175,7 @@ impl<'de, T: NodeList<'de>> de::Deserializer<'de> for Deserializer<T> {
 #[derive(new)]
 pub struct SeqAccess<'a, L> {
     list: &'a mut L,
+    options: Options,
 }
 
 impl<'de, L> de::SeqAccess<'de> for SeqAccess<'_, L>
@@ -126,76 +190,186 @@ where
     {
         self.list
             .next_node()?
-            .map(|node| seed.deserialize(NamedNodeDeserializer::new(node)))
+            .map(|node| seed.deserialize(NamedNodeDeserializer::new(node, self.options)))
             .transpose()
     }
 }
 
-// Please for the love of god someone tell me there's a different way to do this
-pub struct MapAccess<'i, 'a, T: NodeList<'i> + 'a> {
-    fake_list: PhantomData<&'a mut T>,
-    list: NonNull<T>,
-    node: Option<Annotated<'i, NodeContent<'i, 'a>>>,
-}
+/// MapAccess over a node list, where each node name is a key and each node
+/// is the (anonymous) value.
+///
+/// Keys are found by peeking at the upcoming node with a [`Siblings`]
+/// lookahead; the node itself is only taken from the underlying list when its
+/// value is requested. This way, the only borrow of the node list held
+/// between calls is the `&mut` we were given, and a node's content never has
+/// to outlive the call that deserializes it.
+pub struct MapAccess<'i, 'a, T: NodeList<'i>> {
+    list: &'a mut T,
+    value: Option<PendingValue<'i>>,
+
+    /// The fields of the struct being deserialized, if any. Used to detect
+    /// `$kaydle::repeated::`, `$kaydle::keyed::`, and `$kaydle::rest` magics.
+    fields: &'static [&'static str],
 
-#[derive(Debug)]
-enum NodeListReborrowError<E> {
-    /// the reborrower currently has a node
-    Borrowed,
+    /// The names of nodes that were already collected by a
+    /// `$kaydle::repeated::` or `$kaydle::keyed::` magic, and should
+    /// therefore be skipped.
+    collected: Vec<&'static str>,
 
-    /// next_node returned an error
-    Error(E),
+    /// True if the struct has a `$kaydle::rest` field that hasn't been
+    /// yielded yet.
+    rest_pending: bool,
+
+    options: Options,
 }
 
-enum NodeListUseNodeError {
-    /// There wasn't a node available
-    NoNode,
+/// The value associated with the most recently returned key in a
+/// [`MapAccess`]
+enum PendingValue<'i> {
+    /// The key was a node name, and the value is the anonymous node, which is
+    /// the next node in the list.
+    Node,
+
+    /// The key was a `$kaydle::repeated::`, `$kaydle::keyed::`, or
+    /// `$kaydle::rest` magic, and the value is the collected nodes.
+    Collected(CollectedDeserializer<'i>),
 }
 
 impl<'a, 'i, T: NodeList<'i>> MapAccess<'i, 'a, T> {
-    pub fn new(list: &'a mut T) -> Self {
+    pub fn new(list: &'a mut T, fields: &'static [&'static str], options: Options) -> Self {
         Self {
-            fake_list: PhantomData,
-            list: NonNull::from(list),
-            node: None,
+            list,
+            value: None,
+            fields,
+            collected: Vec::new(),
+            rest_pending: fields.contains(&magics::REST),
+            options,
         }
     }
 
-    fn next_node<E, N>(&mut self) -> Result<Option<N>, NodeListReborrowError<nom::Err<E>>>
-    where
-        N: StringBuilder<'i>,
-        E: ParseError<&'i str>,
-        E: TagError<&'i str, &'static str>,
-        E: FromExternalError<&'i str, CharTryFromError>,
+    /// Parse and discard the next node in the list, which was already
+    /// identified with a lookahead.
+    fn skip_node(&mut self) -> Result<(), Error> {
+        let node: Annotated<'i, Node<'i, '_, ()>> = self
+            .list
+            .next_node()?
+            .expect("lookahead found a node, but the list didn't");
 
-        E: ContextError<&'i str, &'static str>,
-    {
-        if self.node.is_some() {
-            return Err(NodeListReborrowError::Borrowed);
+        node.item.content.drain()?;
+        Ok(())
+    }
+
+    /// Get the key for the next entry in the map, which is usually the next
+    /// node name. Nodes collected by a `$kaydle::repeated::`,
+    /// `$kaydle::keyed::`, or `$kaydle::rest` magic are instead returned as a
+    /// single entry, keyed by the magic field name.
+    fn next_key(&mut self) -> Result<Option<KdlString<'i>>, Error> {
+        // If the previous value was never requested, it's discarded.
+        if let Some(PendingValue::Node) = self.value.take() {
+            self.skip_node()?;
         }
 
-        // Invariant preserved: it's only safe for &'a mut T to exist if we
-        // don't also own a NodeContent<'_, 'a>
-        let list: &'a mut T = unsafe { self.list.as_mut() };
-        let node = match list.next_node() {
-            Err(err) => return Err(NodeListReborrowError::Error(err)),
-            Ok(None) => return Ok(None),
-            Ok(Some(node)) => node,
-        };
+        loop {
+            let siblings = self.list.siblings();
 
-        self.node = Some(Annotated {
-            annotation: node.annotation,
-            item: node.item.content,
-        });
+            let name: KdlString<'i> = {
+                let mut lookahead = siblings.clone();
+                let node: Option<Annotated<'i, Node<'i, '_, KdlString<'i>>>> =
+                    lookahead.next_node()?;
 
-        Ok(Some(node.item.name))
-    }
+                match node {
+                    Some(node) => node.item.name,
+
+                    // If there were no unknown nodes, `$kaydle::rest` is
+                    // still yielded (as an empty collection) at the end of
+                    // the list.
+                    None if self.rest_pending => {
+                        self.rest_pending = false;
+                        self.value = Some(PendingValue::Collected(CollectedDeserializer {
+                            nodes: CollectedNodes {
+                                siblings,
+                                filter: NodeFilter::Unknown(self.fields),
+                                options: self.options,
+                            },
+                            kind: CollectionKind::Rest,
+                        }));
+
+                        return Ok(Some(KdlString::from_borrowed(magics::REST)));
+                    }
+                    None => {
+                        // Parse the end of the list from the list itself, so
+                        // that it's finished.
+                        let node: Option<Annotated<'i, Node<'i, '_, ()>>> =
+                            self.list.next_node()?;
+                        debug_assert!(node.is_none());
+                        return Ok(None);
+                    }
+                }
+            };
+
+            if self.collected.iter().any(|&collected| name == collected) {
+                self.skip_node()?;
+                continue;
+            }
+
+            let collection = self.fields.iter().find_map(|&field| {
+                [
+                    (magics::REPEATED, CollectionKind::Sequence),
+                    (magics::KEYED, CollectionKind::Keyed),
+                ]
+                .into_iter()
+                .find_map(|(prefix, kind)| {
+                    field
+                        .strip_prefix(prefix)
+                        .filter(|&collected| name == collected)
+                        .map(|collected| (field, collected, kind))
+                })
+            });
+
+            return match collection {
+                Some((field, collected, kind)) => {
+                    self.skip_node()?;
 
-    fn use_node<R>(
-        &mut self,
-        op: impl for<'b> FnOnce(Annotated<'i, NodeContent<'i, 'b>>) -> R,
-    ) -> Result<R, NodeListUseNodeError> {
-        self.node.take().map(op).ok_or(NodeListUseNodeError::NoNode)
+                    self.collected.push(collected);
+                    self.value = Some(PendingValue::Collected(CollectedDeserializer {
+                        nodes: CollectedNodes {
+                            siblings,
+                            filter: NodeFilter::Name(collected),
+                            options: self.options,
+                        },
+                        kind,
+                    }));
+
+                    Ok(Some(KdlString::from_borrowed(field)))
+                }
+                None if self.rest_pending && NodeFilter::Unknown(self.fields).matches(&name) => {
+                    self.skip_node()?;
+
+                    self.rest_pending = false;
+                    self.value = Some(PendingValue::Collected(CollectedDeserializer {
+                        nodes: CollectedNodes {
+                            siblings,
+                            filter: NodeFilter::Unknown(self.fields),
+                            options: self.options,
+                        },
+                        kind: CollectionKind::Rest,
+                    }));
+
+                    Ok(Some(KdlString::from_borrowed(magics::REST)))
+                }
+                None if self.fields.contains(&magics::REST)
+                    && NodeFilter::Unknown(self.fields).matches(&name) =>
+                {
+                    // This node was already collected by `$kaydle::rest`
+                    self.skip_node()?;
+                    continue;
+                }
+                None => {
+                    self.value = Some(PendingValue::Node);
+                    Ok(Some(name))
+                }
+            };
+        }
     }
 }
 
@@ -209,12 +383,8 @@ where
     where
         K: de::DeserializeSeed<'de>,
     {
-        self.next_node()
-            .map_err(|err| match err {
-                NodeListReborrowError::Borrowed => panic!("called next_key_seed out of order"),
-                NodeListReborrowError::Error(err) => err,
-            })?
-            .map(|node_name| seed.deserialize(StringDeserializer::new(node_name)))
+        self.next_key()?
+            .map(|node_name| seed.deserialize(KeyDeserializer::new(node_name)))
             .transpose()
     }
 
@@ -222,7 +392,691 @@ where
     where
         V: de::DeserializeSeed<'de>,
     {
-        self.use_node(|node| seed.deserialize(AnonymousNodeDeserializer::new(node)))
-            .unwrap_or_else(|_| panic!("called next_value_seed out of order"))
+        match self.value.take() {
+            Some(PendingValue::Node) => {
+                let node: Annotated<'de, Node<'de, '_, ()>> = self
+                    .list
+                    .next_node()?
+                    .expect("lookahead found a node, but the list didn't");
+
+                seed.deserialize(AnonymousNodeDeserializer::new(
+                    Annotated {
+                        annotation: node.annotation,
+                        item: node.item.content,
+                    },
+                    self.options,
+                ))
+            }
+            Some(PendingValue::Collected(collected)) => seed.deserialize(collected),
+            None => panic!("called next_value_seed out of order"),
+        }
+    }
+}
+
+/// How the nodes collected by a [`CollectedDeserializer`] are presented
+#[derive(Debug, Clone, Copy)]
+enum CollectionKind {
+    /// `$kaydle::repeated::`: a sequence of anonymous nodes
+    Sequence,
+
+    /// `$kaydle::keyed::`: a map, where the first argument of each node is
+    /// the key and the rest of the node is the value
+    Keyed,
+
+    /// `$kaydle::rest`: a map, where the name of each node is the key and
+    /// the node is the value
+    Rest,
+}
+
+/// Which nodes in a node list are collected by a [`CollectedNodes`]
+#[derive(Debug, Clone, Copy)]
+enum NodeFilter {
+    /// Nodes with this name
+    Name(&'static str),
+
+    /// Nodes that don't correspond to any of these struct fields
+    Unknown(&'static [&'static str]),
+}
+
+impl NodeFilter {
+    fn matches(&self, name: &KdlString<'_>) -> bool {
+        match *self {
+            NodeFilter::Name(expected) => *name == expected,
+            NodeFilter::Unknown(fields) => !fields.iter().any(|&field| {
+                *name == field
+                    || [magics::REPEATED, magics::KEYED]
+                        .into_iter()
+                        .any(|prefix| field.strip_prefix(prefix) == Some(name.as_str()))
+            }),
+        }
+    }
+}
+
+/// Deserializer for a set of nodes in a node list, as collected by a
+/// `$kaydle::repeated::`, `$kaydle::keyed::`, or `$kaydle::rest` magic.
+struct CollectedDeserializer<'i> {
+    nodes: CollectedNodes<'i>,
+    kind: CollectionKind,
+}
+
+impl<'de> de::Deserializer<'de> for CollectedDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut nodes = self.nodes;
+
+        let value = match self.kind {
+            CollectionKind::Sequence => visitor.visit_seq(&mut nodes)?,
+            CollectionKind::Keyed | CollectionKind::Rest => {
+                let mut access = CollectedMapAccess {
+                    nodes,
+                    kind: self.kind,
+                    value: None,
+                };
+                let value = visitor.visit_map(&mut access)?;
+                nodes = access.nodes;
+                value
+            }
+        };
+
+        // Make sure the visitor didn't leave any of the nodes behind
+        match nodes.next_node()? {
+            None => Ok(value),
+            Some(node) => {
+                node.item.content.drain()?;
+                Err(Error::UnusedNode {
+                    name: node.item.name.into_string(),
+                })
+            }
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        // The collection is always present once it's deserialized. Repeated
+        // and keyed fields without any nodes aren't deserialized at all, so
+        // they get their default (usually `None`) instead.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        // The original list will drain these nodes anyway
+        visitor.visit_unit()
+    }
+}
+
+/// The nodes in a node list that match a [`NodeFilter`]. Used directly as a
+/// sequence of anonymous nodes.
+struct CollectedNodes<'i> {
+    siblings: Siblings<'i>,
+    filter: NodeFilter,
+    options: Options,
+}
+
+impl<'i> CollectedNodes<'i> {
+    /// Skip ahead to the next matching node, and return a lookahead that is
+    /// positioned right before it.
+    fn next_lookahead(&mut self) -> Result<Option<Siblings<'i>>, Error> {
+        loop {
+            let mut lookahead = self.siblings.clone();
+            let node: Annotated<'i, Node<'i, '_, KdlString<'i>>> = match lookahead.next_node()? {
+                None => return Ok(None),
+                Some(node) => node,
+            };
+
+            if self.filter.matches(&node.item.name) {
+                return Ok(Some(self.siblings.clone()));
+            }
+
+            node.item.content.drain()?;
+            self.siblings = lookahead;
+        }
+    }
+
+    /// Get the next matching node, skipping any others
+    fn next_node(&mut self) -> Result<Option<Annotated<'i, Node<'i, '_, KdlString<'i>>>>, Error> {
+        match self.next_lookahead()? {
+            None => Ok(None),
+            Some(_) => Ok(self.siblings.next_node()?),
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for CollectedNodes<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let options = self.options;
+
+        self.next_node()?
+            .map(|node| {
+                seed.deserialize(AnonymousNodeDeserializer::new(
+                    Annotated {
+                        annotation: node.annotation,
+                        item: node.item.content,
+                    },
+                    options,
+                ))
+            })
+            .transpose()
+    }
+}
+
+/// Map access for `$kaydle::keyed::` and `$kaydle::rest` magics. Each node
+/// is visited twice: once to get the key, and once more (from a lookahead
+/// saved in `value`) to deserialize the value.
+struct CollectedMapAccess<'i> {
+    nodes: CollectedNodes<'i>,
+    kind: CollectionKind,
+    value: Option<Siblings<'i>>,
+}
+
+/// Get the first argument of a node, which is used as a map key by
+/// `$kaydle::keyed::`, along with the rest of the node.
+fn split_key<'i, 'p>(
+    name: KdlString<'i>,
+    content: NodeContent<'i, 'p>,
+) -> Result<(KdlValue<'i>, NodeContent<'i, 'p>), Error> {
+    let error = || Error::MissingNodeKey {
+        name: name.as_str().to_owned(),
+    };
+
+    match content.next_event()? {
+        NodeEvent::Argument {
+            argument: RecognizedAnnotationValue { item: key, .. },
+            tail,
+        } => Ok((key, tail)),
+        NodeEvent::Property {
+            property: RecognizedProperty { .. },
+            tail,
+        } => {
+            tail.drain()?;
+            Err(error())
+        }
+        NodeEvent::Children { children } => {
+            children.drain()?;
+            Err(error())
+        }
+        NodeEvent::End => Err(error()),
+    }
+}
+
+impl<'de> de::MapAccess<'de> for CollectedMapAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let lookahead = match self.nodes.next_lookahead()? {
+            None => return Ok(None),
+            Some(lookahead) => lookahead,
+        };
+
+        let node = self
+            .nodes
+            .next_node()?
+            .expect("lookahead found a node, so next_node should too");
+
+        let key = match self.kind {
+            CollectionKind::Rest => {
+                node.item.content.drain()?;
+                seed.deserialize(KeyDeserializer::<Error>::new(node.item.name))?
+            }
+            _ => {
+                let (key, tail) = split_key(node.item.name, node.item.content)?;
+                tail.drain()?;
+
+                // String keys can be parsed into other types, just like
+                // node names
+                match key {
+                    KdlValue::String(key) => {
+                        seed.deserialize(KeyDeserializer::<Error>::new(key))?
+                    }
+                    key => seed.deserialize(RawValueDeserializer::<Error>::new(key))?,
+                }
+            }
+        };
+
+        self.value = Some(lookahead);
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let mut lookahead = self
+            .value
+            .take()
+            .expect("called next_value_seed out of order");
+
+        let node: Annotated<'de, Node<'de, '_, KdlString<'de>>> = lookahead
+            .next_node()?
+            .expect("lookahead should contain the node");
+
+        let content = match self.kind {
+            CollectionKind::Rest => node.item.content,
+            _ => split_key(node.item.name, node.item.content)?.1,
+        };
+
+        seed.deserialize(AnonymousNodeDeserializer::new(
+            Annotated {
+                annotation: node.annotation,
+                item: content,
+            },
+            self.nodes.options,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use kaydle_primitives::{
+        limits::{LimitError, Limits},
+        node::Document,
+    };
+    use serde::{de::IgnoredAny, Deserialize};
+
+    use super::Deserializer;
+    use crate::serde::de::{from_str, Error, Options};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Plugins {
+        name: String,
+
+        #[serde(rename = "$kaydle::repeated::plugin", default)]
+        plugins: Vec<Plugin>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Plugin {
+        path: String,
+        enabled: bool,
+    }
+
+    #[test]
+    fn repeated_nodes() {
+        let plugins: Plugins = from_str(
+            r#"
+            plugin path="auth.so" enabled=true
+            name "server"
+            plugin path="log.so" enabled=false
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            plugins,
+            Plugins {
+                name: "server".to_owned(),
+                plugins: vec![
+                    Plugin {
+                        path: "auth.so".to_owned(),
+                        enabled: true
+                    },
+                    Plugin {
+                        path: "log.so".to_owned(),
+                        enabled: false
+                    },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn repeated_nodes_absent() {
+        let plugins: Plugins = from_str(r#"name "server""#).expect("failed to deserialize");
+
+        assert_eq!(
+            plugins,
+            Plugins {
+                name: "server".to_owned(),
+                plugins: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn repeated_nodes_in_children() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Document {
+            server: Plugins,
+        }
+
+        let document: Document = from_str(
+            r#"
+            server {
+                plugin path="a.so" enabled=true
+                name "alpha"
+            }
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document,
+            Document {
+                server: Plugins {
+                    name: "alpha".to_owned(),
+                    plugins: vec![Plugin {
+                        path: "a.so".to_owned(),
+                        enabled: true
+                    }],
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn repeated_nodes_unused() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Pair {
+            #[serde(rename = "$kaydle::repeated::value")]
+            values: (i32, i32),
+        }
+
+        let err = from_str::<Pair>("value 1; value 2; value 3").expect_err("third value is unused");
+        assert!(matches!(err, Error::UnusedNode { ref name } if name == "value"));
+    }
+
+    #[test]
+    fn optional_collections() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Collections {
+            #[serde(rename = "$kaydle::repeated::item", default)]
+            items: Option<Vec<i32>>,
+
+            #[serde(rename = "$kaydle::keyed::entry", default)]
+            entries: Option<HashMap<String, i32>>,
+
+            #[serde(rename = "$kaydle::rest", default)]
+            rest: Option<HashMap<String, i32>>,
+        }
+
+        let collections: Collections =
+            from_str("item 1\nitem 2\nentry \"a\" 3\nother 4").expect("failed to deserialize");
+        assert_eq!(
+            collections,
+            Collections {
+                items: Some(vec![1, 2]),
+                entries: Some(HashMap::from([("a".to_owned(), 3)])),
+                rest: Some(HashMap::from([("other".to_owned(), 4)])),
+            }
+        );
+
+        let collections: Collections = from_str("").expect("failed to deserialize");
+        assert_eq!(
+            collections,
+            Collections {
+                items: None,
+                entries: None,
+                rest: Some(HashMap::new()),
+            }
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Servers {
+        #[serde(rename = "$kaydle::keyed::server")]
+        servers: HashMap<String, Server>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[test]
+    fn keyed_nodes() {
+        let servers: Servers = from_str(
+            r#"
+            server "alpha" {
+                host "alpha.example.com"
+                port 80
+            }
+            server "beta" {
+                host "beta.example.com"
+                port 8080
+            }
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            servers,
+            Servers {
+                servers: HashMap::from([
+                    (
+                        "alpha".to_owned(),
+                        Server {
+                            host: "alpha.example.com".to_owned(),
+                            port: 80,
+                        }
+                    ),
+                    (
+                        "beta".to_owned(),
+                        Server {
+                            host: "beta.example.com".to_owned(),
+                            port: 8080,
+                        }
+                    ),
+                ])
+            }
+        );
+    }
+
+    #[test]
+    fn keyed_nodes_with_properties() {
+        let servers: Servers = from_str(
+            r#"
+            server "alpha" host="alpha.example.com" port=80
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            servers.servers["alpha"],
+            Server {
+                host: "alpha.example.com".to_owned(),
+                port: 80,
+            }
+        );
+    }
+
+    #[test]
+    fn keyed_nodes_missing_key() {
+        let err = from_str::<Servers>(r#"server host="alpha.example.com" port=80"#)
+            .expect_err("server has no key");
+
+        assert!(matches!(err, Error::MissingNodeKey { ref name } if name == "server"));
+    }
+
+    #[test]
+    fn nested_children_map_access() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Leaf {
+            value: i32,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Branch {
+            left: Leaf,
+            right: Leaf,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Tree {
+            root: Branch,
+            size: u32,
+        }
+
+        let tree: Tree = from_str(
+            r#"
+            root {
+                ignored 1 2 3 {
+                    deeply { nested; }
+                }
+                left { value 1; extra "ignored"; }
+                right { value 2; }
+            }
+            size 2
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            tree,
+            Tree {
+                root: Branch {
+                    left: Leaf { value: 1 },
+                    right: Leaf { value: 2 },
+                },
+                size: 2,
+            }
+        );
+    }
+
+    /// Map access must cope with keys whose values are never requested
+    #[test]
+    fn map_keys_without_values() {
+        struct Keys(Vec<String>);
+
+        impl<'de> Deserialize<'de> for Keys {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl<'de> serde::de::Visitor<'de> for Visitor {
+                    type Value = Keys;
+
+                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        formatter.write_str("a map")
+                    }
+
+                    fn visit_map<A: serde::de::MapAccess<'de>>(
+                        self,
+                        mut map: A,
+                    ) -> Result<Keys, A::Error> {
+                        let mut keys = Vec::new();
+
+                        while let Some(key) = map.next_key()? {
+                            keys.push(key);
+                        }
+
+                        Ok(Keys(keys))
+                    }
+                }
+
+                deserializer.deserialize_map(Visitor)
+            }
+        }
+
+        let Keys(keys) =
+            from_str("a 1\nb { c 2; }\nd key=\"value\"").expect("failed to deserialize");
+        assert_eq!(keys, ["a", "b", "d"]);
+    }
+
+    #[test]
+    fn direct_deserializer_depth() {
+        #[derive(Deserialize, Debug)]
+        struct Tree {
+            #[serde(rename = "$kaydle::repeated::tree", default)]
+            children: Vec<Tree>,
+        }
+
+        let tree = Tree::deserialize(Deserializer::new(Document::new("tree { tree; }")))
+            .expect("failed to deserialize");
+        assert_eq!(tree.children.len(), 1);
+
+        // A deserializer created directly doesn't scan the document for
+        // exceeded limits up front, so the depth limit is enforced as the
+        // children are deserialized
+        let options = Options::new().limits(Limits {
+            max_depth: 16,
+            ..Limits::default()
+        });
+        let input = format!("{}{}", "tree {\n".repeat(1000), "}\n".repeat(1000));
+        let err = Tree::deserialize(Deserializer::new(Document::new(&input)).with_options(options))
+            .expect_err("nesting limit");
+        assert!(matches!(
+            err,
+            Error::LimitExceeded(LimitError::Depth { limit: 16, offset }) if offset == 16 * 7 + 5
+        ));
+
+        // Ignored nodes are drained without recursing
+        let input = format!("{}{}", "a {".repeat(100_000), "}".repeat(100_000));
+        IgnoredAny::deserialize(Deserializer::new(Document::new(&input))).expect("failed to drain");
+    }
+
+    #[test]
+    fn limits() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Tree {
+            #[serde(rename = "$kaydle::repeated::tree", default)]
+            children: Vec<Tree>,
+        }
+
+        fn nested(depth: usize) -> String {
+            format!("{}{}", "tree {\n".repeat(depth), "}\n".repeat(depth))
+        }
+
+        fn depth(tree: &Tree) -> usize {
+            tree.children
+                .first()
+                .map(|child| depth(child) + 1)
+                .unwrap_or(0)
+        }
+
+        let tree: Tree = from_str(&nested(128)).expect("failed to deserialize");
+        assert_eq!(depth(&tree), 128);
+
+        let err = from_str::<Tree>(&nested(100_000)).expect_err("nesting limit");
+        assert!(matches!(
+            err,
+            Error::LimitExceeded(LimitError::Depth { limit: 128, .. })
+        ));
+
+        let limits = Limits {
+            max_depth: 4,
+            ..Limits::default()
+        };
+        let err = Options::new()
+            .limits(limits)
+            .from_str::<Tree>(&nested(5))
+            .expect_err("nesting limit");
+        assert!(matches!(
+            err,
+            Error::LimitExceeded(LimitError::Depth { limit: 4, .. })
+        ));
+
+        // Error tokens in the limits scan mustn't hide the rest of the
+        // document from the depth limit
+        let input = format!("r#x {}", "{ a ".repeat(200_000));
+        let err = from_str::<IgnoredAny>(&input).expect_err("nesting limit");
+        assert!(matches!(
+            err,
+            Error::LimitExceeded(LimitError::Depth { limit: 128, .. })
+        ));
     }
 }
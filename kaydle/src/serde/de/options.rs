@@ -0,0 +1,294 @@
+use std::marker::PhantomData;
+
+use kaydle_primitives::{
+    annotation::AnnotatedValue, limits::Limits, node::Document, reserved, string::KdlString,
+};
+use serde::de;
+
+use super::{
+    node_list, DuplicateProperties, Error, NameMatching, PropertiesAndChildren, StreamDeserializer,
+    WildcardNames,
+};
+
+/// Options that control how a KDL document is deserialized. Options are
+/// usually built from [`Options::new`] (or one of the presets, [`strict`]
+/// and [`permissive`]) and then used directly to deserialize a document:
+///
+/// ```
+/// use kaydle::serde::de::Options;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// let options = Options::new().lenient_scalars(true);
+/// let server: Server = options.from_str(r#"port "8080""#).unwrap();
+///
+/// assert_eq!(server, Server { port: 8080 });
+/// ```
+///
+/// The fields are also public, so options can be built with struct update
+/// syntax, like [`Limits`].
+///
+/// [`strict`]: Options::strict
+/// [`permissive`]: Options::permissive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    /// If true, values with one of the type annotations reserved by the KDL
+    /// spec, like `(u8)` or `(ipv4)`, must be compatible with that
+    /// annotation, or deserialization fails with
+    /// [`Error::ReservedAnnotation`]. See [`kaydle_primitives::reserved`]
+    /// for the checks. Defaults to false, in which case annotations are
+    /// never checked.
+    pub validate_annotations: bool,
+
+    /// If true, scalar values are converted to the type being deserialized
+    /// where that's unambiguous:
+    ///
+    /// - Strings containing a number, like `"8080"`, can be deserialized as
+    ///   numbers.
+    /// - The strings `"true"`, `"yes"`, `"on"`, and `"1"` can be deserialized
+    ///   as `true`, and `"false"`, `"no"`, `"off"`, and `"0"` as `false`
+    ///   (ignoring case).
+    /// - Floats with no fractional part, like `1e3`, can be deserialized as
+    ///   integers.
+    /// - Integers can be deserialized as floats.
+    ///
+    /// Defaults to false, in which case values must already have the right
+    /// type.
+    pub lenient_scalars: bool,
+
+    /// The [`Limits`] that documents are checked against before they're
+    /// deserialized. Defaults to [`Limits::default`].
+    pub limits: Limits,
+
+    /// How the names of named nodes are matched against the names of the
+    /// types they're deserialized into. Defaults to
+    /// [`NameMatching::Exact`].
+    pub name_matching: NameMatching,
+
+    /// Which node names match any type, regardless of
+    /// [`name_matching`][Self::name_matching]. Defaults to
+    /// [`WildcardNames::None`].
+    pub wildcard_names: WildcardNames,
+
+    /// What happens when a node has more than one property with the same
+    /// key. Defaults to [`DuplicateProperties::Keep`].
+    pub duplicate_properties: DuplicateProperties,
+
+    /// Where the fields of a struct come from when a node has both
+    /// properties and children. Defaults to
+    /// [`PropertiesAndChildren::Exclusive`].
+    pub properties_and_children: PropertiesAndChildren,
+}
+
+impl Options {
+    /// The default options. Annotations aren't checked, and values must
+    /// already have the right type.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options for documents that should be checked as carefully as
+    /// possible: reserved type annotations are validated, values are never
+    /// converted, node names must exactly match type names, and duplicate
+    /// properties are rejected.
+    pub fn strict() -> Self {
+        Self::new()
+            .validate_annotations(true)
+            .duplicate_properties(DuplicateProperties::Reject)
+    }
+
+    /// Options for hand-written documents, which accept as much as
+    /// possible: scalar values are converted to the requested type,
+    /// annotations aren't checked, node names are matched to type names
+    /// ignoring case, nodes called `-` match any type, the last of several
+    /// duplicate properties is used, and properties and children are merged.
+    pub fn permissive() -> Self {
+        Self::new()
+            .lenient_scalars(true)
+            .name_matching(NameMatching::CaseInsensitive)
+            .wildcard_names(WildcardNames::Dash)
+            .duplicate_properties(DuplicateProperties::Last)
+            .properties_and_children(PropertiesAndChildren::Merge)
+    }
+
+    /// Set [`validate_annotations`][Self::validate_annotations]
+    pub fn validate_annotations(self, validate_annotations: bool) -> Self {
+        Self {
+            validate_annotations,
+            ..self
+        }
+    }
+
+    /// Set [`lenient_scalars`][Self::lenient_scalars]
+    pub fn lenient_scalars(self, lenient_scalars: bool) -> Self {
+        Self {
+            lenient_scalars,
+            ..self
+        }
+    }
+
+    /// Set the [`limits`][Self::limits]
+    pub fn limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Set [`name_matching`][Self::name_matching]
+    pub fn name_matching(self, name_matching: NameMatching) -> Self {
+        Self {
+            name_matching,
+            ..self
+        }
+    }
+
+    /// Set [`wildcard_names`][Self::wildcard_names]
+    pub fn wildcard_names(self, wildcard_names: WildcardNames) -> Self {
+        Self {
+            wildcard_names,
+            ..self
+        }
+    }
+
+    /// Set [`duplicate_properties`][Self::duplicate_properties]
+    pub fn duplicate_properties(self, duplicate_properties: DuplicateProperties) -> Self {
+        Self {
+            duplicate_properties,
+            ..self
+        }
+    }
+
+    /// Set [`properties_and_children`][Self::properties_and_children]
+    pub fn properties_and_children(self, properties_and_children: PropertiesAndChildren) -> Self {
+        Self {
+            properties_and_children,
+            ..self
+        }
+    }
+
+    /// Deserialize something from a string containing a KDL document, using
+    /// these options. The document is checked against the
+    /// [`limits`][Self::limits] before it's deserialized.
+    pub fn from_str<'a, T: de::Deserialize<'a>>(&self, input: &'a str) -> Result<T, Error> {
+        self.from_str_seed(PhantomData, input)
+    }
+
+    /// Deserialize something from a string containing a KDL document with a
+    /// [`DeserializeSeed`][de::DeserializeSeed], using these options. This is
+    /// how stateful deserializers, like a
+    /// [`NodeRegistry`][crate::serde::NodeRegistry], are used with options.
+    pub fn from_str_seed<'a, S: de::DeserializeSeed<'a>>(
+        &self,
+        seed: S,
+        input: &'a str,
+    ) -> Result<S::Value, Error> {
+        self.limits.check(input)?;
+
+        let document = Document::new(input);
+        let deserializer = node_list::Deserializer::new(document).with_options(*self);
+        seed.deserialize(deserializer)
+            .map_err(|err| err.locate(input))
+    }
+
+    /// Create a [`StreamDeserializer`] over the top-level nodes in a string
+    /// containing a KDL document, using these options.
+    pub fn stream<'a, T>(&self, input: &'a str) -> StreamDeserializer<'a, T> {
+        StreamDeserializer::with_options(input, *self)
+    }
+
+    /// Check that a node name matches the name of the type it's being
+    /// deserialized into
+    pub(super) fn check_type_name(
+        &self,
+        node_name: KdlString<'_>,
+        type_name: &'static str,
+    ) -> Result<(), Error> {
+        match self.name_matching.matches(node_name.as_str(), type_name) {
+            true => Ok(()),
+            false => Err(Error::TypeNameMismatch {
+                node_name: node_name.into_string(),
+                type_name,
+                expected: self.name_matching.expected_name(type_name).into_owned(),
+            }),
+        }
+    }
+
+    /// Check a value against its annotation, if these options call for it
+    pub(super) fn check_value(&self, value: &AnnotatedValue<'_>) -> Result<(), Error> {
+        match value.annotation {
+            Some(ref annotation) if self.validate_annotations => {
+                reserved::validate(annotation.as_str(), &value.item).map_err(Error::from)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kaydle_primitives::limits::Limits;
+    use serde::Deserialize;
+
+    use super::Options;
+    use crate::serde::de::{
+        from_str_with_options, DuplicateProperties, Error, NameMatching, PropertiesAndChildren,
+        WildcardNames,
+    };
+
+    #[test]
+    fn options_builder() {
+        assert_eq!(Options::new(), Options::default());
+        assert_eq!(
+            Options::strict(),
+            Options {
+                validate_annotations: true,
+                duplicate_properties: DuplicateProperties::Reject,
+                ..Options::default()
+            }
+        );
+        assert_eq!(
+            Options::permissive(),
+            Options::new()
+                .lenient_scalars(true)
+                .name_matching(NameMatching::CaseInsensitive)
+                .wildcard_names(WildcardNames::Dash)
+                .duplicate_properties(DuplicateProperties::Last)
+                .properties_and_children(PropertiesAndChildren::Merge)
+        );
+
+        let limits = Limits {
+            max_depth: 0,
+            ..Limits::default()
+        };
+
+        let options = Options::permissive().limits(limits);
+        assert_eq!(options.limits, limits);
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename = "item")]
+        struct Item(u32);
+
+        let items: Vec<Item> = options.from_str(r#"item "1"; item 2"#).expect("permissive");
+        assert_eq!(items, [Item(1), Item(2)]);
+
+        let items: Vec<Item> =
+            from_str_with_options(r#"item "1"; item 2"#, options).expect("permissive");
+        assert_eq!(items, [Item(1), Item(2)]);
+
+        let err = options
+            .from_str::<Vec<Item>>("item {\n}")
+            .expect_err("too deep");
+        assert!(matches!(err, Error::LimitExceeded(_)));
+
+        let items: Vec<Item> = options
+            .stream(r#"item "3"; item 4"#)
+            .collect::<Result<_, _>>()
+            .expect("permissive stream");
+        assert_eq!(items, [Item(3), Item(4)]);
+
+        let mut stream = options.stream::<Item>("item {\n}");
+        assert!(matches!(stream.next(), Some(Err(Error::LimitExceeded(_)))));
+    }
+}
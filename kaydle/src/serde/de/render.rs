@@ -0,0 +1,282 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
+
+use super::Error;
+
+impl Error {
+    /// The byte range of the source document that this error refers to, if
+    /// it's known. Only parse errors and exceeded limits have a location.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Error::ParseError(err) => {
+                let offset = err.error.offset();
+                Some(offset..offset + err.error.span_len())
+            }
+            Error::LimitExceeded(err) => {
+                let offset = err.offset();
+                Some(offset..offset + 1)
+            }
+            _ => None,
+        }
+    }
+
+    /// A hint for how to fix this error, if there's a useful one.
+    pub fn help(&self) -> Option<String> {
+        match self {
+            Error::ParseError(err) => err.error.help().map(str::to_owned),
+            Error::LimitExceeded(_) => Some(
+                "if the document is trusted, raise the limit with `Options::limits`".to_owned(),
+            ),
+            Error::PrimitiveFromNodelist => Some(
+                "a document or children block is a list of nodes; deserialize it as a struct or \
+                map with a field for each node"
+                    .to_owned(),
+            ),
+            Error::PrimitiveFromNamedNode => Some(
+                "wrap the type in a newtype struct, renamed to match the node with \
+                `#[serde(rename = \"...\")]`"
+                    .to_owned(),
+            ),
+            Error::TypeNameMismatch {
+                node_name,
+                type_name: _,
+                expected,
+            } => Some(format!(
+                "rename the node to `{expected}`, use `#[serde(rename = \"{node_name}\")]` on \
+                the type, or choose a different `Options::name_matching`"
+            )),
+            Error::UnusedNode { name } => Some(format!(
+                "add a field for `{name}` to the type, or remove it from the document"
+            )),
+            Error::MissingNodeKey { name } => Some(format!(
+                "add an argument to use as the key, like `{name} \"key\"`"
+            )),
+            Error::InvalidAnnotatedValue => Some(
+                "a struct with a `$kaydle::annotation` field needs exactly one other field, for \
+                the value"
+                    .to_owned(),
+            ),
+            Error::UnfinishedNode { .. } => Some(
+                "add a field to the type for the leftover content, or remove it from the document"
+                    .to_owned(),
+            ),
+            Error::ReservedAnnotation(_) => Some(
+                "fix the value, or remove the annotation if the value isn't meant to have that \
+                type"
+                    .to_owned(),
+            ),
+            Error::NonNewtypeFromAnnotatedValue => {
+                Some("use a newtype variant, like `Variant(T)`".to_owned())
+            }
+            Error::DuplicateProperty { key } => Some(format!(
+                "remove all but one `{key}=` property, or choose a different \
+                `Options::duplicate_properties`"
+            )),
+            Error::InvalidEncodedBytes { encoding, .. } => Some(format!(
+                "check that the string is valid {encoding}, or remove the annotation"
+            )),
+            Error::InvalidByte { .. } => Some(
+                "byte buffers can be written as integers from 0 to 255, like `data 0x01 0xff`"
+                    .to_owned(),
+            ),
+            Error::Custom(_)
+            | Error::IncompatibleNode
+            | Error::IncompatibleContent { .. }
+            | Error::UnknownVariant { .. } => None,
+        }
+    }
+
+    /// The contexts that were being parsed when a parse error happened,
+    /// formatted like `while parsing node → value`.
+    fn context_note(&self) -> Option<String> {
+        match self {
+            Error::ParseError(err) => err.context.split_first().map(|(first, rest)| {
+                rest.iter()
+                    .fold(format!("while parsing {first}"), |note, ctx| {
+                        note + " → " + ctx
+                    })
+            }),
+            _ => None,
+        }
+    }
+
+    /// Render this error for humans, in the style of rustc: the offending
+    /// line of `source` is shown with the error underlined, along with what
+    /// was being parsed and a hint for fixing it, if any.
+    ///
+    /// `source` must be the string the error came from; see
+    /// [`Error::ParseError`] for when offsets are relative to it.
+    ///
+    /// ```
+    /// use kaydle::serde::from_str;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Package {
+    ///     name: String,
+    ///     version: u64,
+    /// }
+    ///
+    /// let source = "name \"kaydle\"\nversion 0x1_0000_0000_0000_0000\n";
+    /// let err = from_str::<Package>(source).unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.render(source).to_string(),
+    ///     "\
+    /// error: number 0x1_0000_0000_0000_0000 is out of range for u64
+    ///  --> line 2, column 9
+    ///   |
+    /// 2 | version 0x1_0000_0000_0000_0000
+    ///   |         ^^^^^^^^^^^^^^^^^^^^^^^
+    ///   = note: while parsing value → number
+    ///   = help: if this is meant to be text rather than a number, quote it
+    /// "
+    /// );
+    /// ```
+    pub fn render<'a>(&'a self, source: &'a str) -> Rendered<'a> {
+        Rendered {
+            error: self,
+            source,
+        }
+    }
+}
+
+/// An [`Error`][enum@Error] rendered along with the source document it came from. Created
+/// by [`Error::render`].
+#[derive(Debug, Clone, Copy)]
+pub struct Rendered<'a> {
+    error: &'a Error,
+    source: &'a str,
+}
+
+impl Display for Rendered<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.error {
+            Error::ParseError(err) => writeln!(f, "error: {}", err.error.message())?,
+            err => writeln!(f, "error: {err}")?,
+        }
+
+        let mut gutter = 0;
+
+        if let Some(span) = self.error.span() {
+            let source = self.source;
+
+            // Clamp the span to the source, in case the error came from
+            // somewhere else
+            let mut start = span.start.min(source.len());
+            while !source.is_char_boundary(start) {
+                start -= 1;
+            }
+
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source[start..]
+                .find('\n')
+                .map_or(source.len(), |i| start + i);
+            let end = span.end.clamp(start, line_end);
+
+            let line = source[line_start..line_end].trim_end_matches('\r');
+            let line_number = (source[..line_start].matches('\n').count() + 1).to_string();
+            let column = source[line_start..start].chars().count() + 1;
+
+            // Keep tabs in the padding, so that the carets line up with the
+            // line above no matter how wide the terminal renders them
+            let padding: String = source[line_start..start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = source.get(start..end).map_or(0, |s| s.chars().count());
+
+            gutter = line_number.len();
+
+            writeln!(f, "{:gutter$}--> line {line_number}, column {column}", "")?;
+            writeln!(f, "{:gutter$} |", "")?;
+            writeln!(f, "{line_number} | {line}")?;
+            writeln!(f, "{:gutter$} | {padding}{}", "", "^".repeat(carets.max(1)))?;
+        }
+
+        if let Some(note) = self.error.context_note() {
+            writeln!(f, "{:gutter$} = note: {note}", "")?;
+        }
+
+        if let Some(help) = self.error.help() {
+            writeln!(f, "{:gutter$} = help: {help}", "")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for Error {
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.help().map(|help| Box::new(help) as Box<dyn Display>)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let span = self.span()?;
+        let label = miette::LabeledSpan::new_with_span(self.context_note(), span);
+
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::serde::de::from_str;
+
+    #[test]
+    fn render() {
+        #[derive(Deserialize, Debug)]
+        struct Config {
+            #[allow(dead_code)]
+            name: String,
+        }
+
+        let source = "name \"server\"\n\tpath key=\"a\\qb\"\n";
+        let err = from_str::<Config>(source).unwrap_err();
+        assert_eq!(
+            err.render(source).to_string(),
+            "error: invalid escape sequence in string\n \
+            --> line 2, column 14\n  \
+             |\n\
+            2 | \tpath key=\"a\\qb\"\n  \
+             | \t            ^\n  \
+             = note: while parsing property → value → string → escaped string → escape\n  \
+             = help: valid escapes are \\n, \\r, \\t, \\\\, \\/, \\\", \\b, \\f, and \\u{...}\n"
+        );
+
+        // Errors without a location just get the message and help
+        let source = "port 80\n";
+        let err = from_str::<u16>(source).unwrap_err();
+        assert_eq!(
+            err.render(source).to_string(),
+            "error: can't deserialize primitive type from node list\n \
+            = help: a document or children block is a list of nodes; deserialize it as a \
+            struct or map with a field for each node\n"
+        );
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn miette_diagnostic() {
+        use std::collections::HashMap;
+
+        use miette::Diagnostic;
+
+        let source = "a {\n    b\n";
+        let err = from_str::<HashMap<String, ()>>(source).unwrap_err();
+
+        assert_eq!(
+            Diagnostic::help(&err).unwrap().to_string(),
+            "add a `}` to close the children block"
+        );
+
+        let labels: Vec<_> = err.labels().unwrap().collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].offset(), source.len());
+    }
+}
@@ -0,0 +1,204 @@
+use std::marker::PhantomData;
+
+use kaydle_primitives::{
+    annotation::Annotated,
+    limits::{Limits, NodeChecker},
+    node::{Document, Node, NodeList},
+    string::KdlString,
+};
+use serde::de;
+
+use super::{named_node::Deserializer as NamedNodeDeserializer, Error, Options};
+
+/// Iterator that deserializes each top-level node of a KDL document, one at
+/// a time, as a named node. Unlike [`from_str`][super::from_str], which
+/// deserializes the whole document into a single value, this only holds one
+/// node's worth of deserialized data at a time, and can be stopped early.
+///
+/// Create one with [`StreamDeserializer::new`] or
+/// [`Deserializer::into_stream`][super::Deserializer::into_stream].
+///
+/// The iterator is fused: after it returns an error, it returns `None`
+/// forever, because the document can't be reliably resumed.
+///
+/// When created with [`new`][Self::new], [`with_limits`][Self::with_limits],
+/// or [`with_options`][Self::with_options], each node is checked against the
+/// [`Limits`] just before it's deserialized, so nothing after it needs to be
+/// scanned yet. [`max_nodes`][Limits::max_nodes] counts every node returned
+/// so far, including their descendants.
+pub struct StreamDeserializer<'de, T> {
+    document: Document<'de>,
+
+    /// The input the stream started from, for locating parse errors
+    input: &'de str,
+
+    /// Checks each node against the limits, if there are any
+    checker: Option<NodeChecker>,
+    options: Options,
+    done: bool,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<'de, T> StreamDeserializer<'de, T> {
+    /// Create a new stream over the top-level nodes in a string containing a
+    /// KDL document, using the default [`Limits`].
+    pub fn new(input: &'de str) -> Self {
+        Self::with_options(input, Options::default())
+    }
+
+    /// Create a new stream over the top-level nodes in a string containing a
+    /// KDL document, using custom [`Limits`].
+    pub fn with_limits(input: &'de str, limits: Limits) -> Self {
+        Self::with_options(input, Options::new().limits(limits))
+    }
+
+    /// Create a new stream over the top-level nodes in a string containing a
+    /// KDL document, using custom [`Options`] (including their limits).
+    pub fn with_options(input: &'de str, options: Options) -> Self {
+        Self {
+            checker: Some(NodeChecker::new(options.limits)),
+            ..Self::from_document(Document::new(input), options)
+        }
+    }
+
+    pub(super) fn from_document(document: Document<'de>, options: Options) -> Self {
+        Self {
+            input: document.remaining(),
+            document,
+            checker: None,
+            options,
+            done: false,
+            output: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>> StreamDeserializer<'de, T> {
+    fn next_value(&mut self) -> Result<Option<T>, Error> {
+        if let Some(checker) = &mut self.checker {
+            let remaining = self.document.remaining();
+
+            checker
+                .check_node(remaining)
+                .map_err(|err| err.shifted(self.input.len() - remaining.len()))?;
+        }
+
+        let node: Annotated<'de, Node<'de, '_, KdlString<'de>>> = match self.document.next_node()? {
+            None => return Ok(None),
+            Some(node) => node,
+        };
+
+        T::deserialize(NamedNodeDeserializer::new(node, self.options)).map(Some)
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>> Iterator for StreamDeserializer<'de, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self
+            .next_value()
+            .map_err(|err| err.locate(self.input))
+            .transpose();
+
+        // After an error, the node that caused it may be only partially
+        // parsed, so there's no way to continue to the next node.
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+
+        result
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>> std::iter::FusedIterator for StreamDeserializer<'de, T> {}
+
+#[cfg(test)]
+mod tests {
+    use kaydle_primitives::{
+        limits::{LimitError, Limits},
+        node::Document,
+    };
+    use serde::Deserialize;
+
+    use super::StreamDeserializer;
+    use crate::serde::de::{Deserializer, Error};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename = "event")]
+    struct Event {
+        id: u32,
+    }
+
+    #[test]
+    fn limits_per_node() {
+        // Nodes before the one that exceeds a limit are still returned, and
+        // the node count includes every node so far
+        let limits = Limits {
+            max_nodes: 3,
+            ..Limits::default()
+        };
+
+        let input = "event id=1\nevent id=2\nevent id=3\nevent id=4";
+        let mut events = StreamDeserializer::<Event>::with_limits(input, limits);
+
+        assert_eq!(events.next().unwrap().unwrap(), Event { id: 1 });
+        assert_eq!(events.next().unwrap().unwrap(), Event { id: 2 });
+        assert_eq!(events.next().unwrap().unwrap(), Event { id: 3 });
+        assert!(matches!(
+            events.next(),
+            Some(Err(Error::LimitExceeded(LimitError::NodeCount {
+                limit: 3,
+                offset: 33,
+            })))
+        ));
+        assert!(events.next().is_none());
+
+        let limits = Limits {
+            max_depth: 1,
+            ..Limits::default()
+        };
+
+        let input = "event id=1\nevent id=2\nevent id=3 { a { b; }; }";
+        let mut events = StreamDeserializer::<Event>::with_limits(input, limits);
+
+        assert_eq!(events.next().unwrap().unwrap(), Event { id: 1 });
+        assert_eq!(events.next().unwrap().unwrap(), Event { id: 2 });
+        assert!(matches!(
+            events.next(),
+            Some(Err(Error::LimitExceeded(LimitError::Depth {
+                limit: 1,
+                offset: 37,
+            })))
+        ));
+    }
+
+    #[test]
+    fn stream_deserializer() {
+        let events: Vec<Event> = StreamDeserializer::new("event id=1\nevent id=2\nevent id=3")
+            .collect::<Result<_, _>>()
+            .expect("failed to deserialize");
+        assert_eq!(events, [Event { id: 1 }, Event { id: 2 }, Event { id: 3 }]);
+
+        // Stopping early doesn't parse the rest of the document
+        let mut events =
+            Deserializer::new(Document::new("event id=1\nevent {{{ broken")).into_stream::<Event>();
+        assert_eq!(events.next().unwrap().unwrap(), Event { id: 1 });
+
+        // Errors end the stream
+        let mut events = StreamDeserializer::<Event>::new("event id=1\nother id=2\nevent id=3");
+        assert_eq!(events.next().unwrap().unwrap(), Event { id: 1 });
+        assert!(matches!(
+            events.next(),
+            Some(Err(Error::TypeNameMismatch {
+                type_name: "event",
+                ..
+            }))
+        ));
+        assert!(events.next().is_none());
+    }
+}
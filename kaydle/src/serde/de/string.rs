@@ -4,8 +4,9 @@ use derive_new::new;
 use kaydle_primitives::string::KdlString;
 use serde::{de, forward_to_deserialize_any};
 
-// TODO: enum support.
-
+/// A string deserializer, used for node names, property keys, and string
+/// values. In addition to strings, it can be deserialized as a unit enum
+/// variant.
 #[derive(Debug, new)]
 pub struct Deserializer<'i, E> {
     string: KdlString<'i>,
@@ -25,6 +26,183 @@ impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
         bytes byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum identifier ignored_any
+        tuple_struct map struct identifier ignored_any
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_enum(self)
+    }
+}
+
+impl<'de, E: de::Error> de::EnumAccess<'de> for Deserializer<'de, E> {
+    type Error = E;
+    type Variant = UnitVariantAccess<E>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+            .map(|variant| (variant, UnitVariantAccess::new()))
+    }
+}
+
+/// VariantAccess for enums that were deserialized from a plain string. Only
+/// unit variants are supported, since a string has no content.
+#[derive(Debug, new)]
+pub struct UnitVariantAccess<E> {
+    error: PhantomData<E>,
+}
+
+impl<'de, E: de::Error> de::VariantAccess<'de> for UnitVariantAccess<E> {
+    type Error = E;
+
+    #[inline]
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        Err(E::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    #[inline]
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(E::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    #[inline]
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(E::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use crate::serde::de::{from_str, Error};
+
+    #[derive(Deserialize, Debug, PartialEq, Eq, Hash)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Info,
+    }
+
+    #[test]
+    fn unit_variants_from_strings() {
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Document {
+            log_level: Level,
+            levels: Vec<Level>,
+            thresholds: HashMap<Level, i32>,
+        }
+
+        let document: Document = from_str(
+            r#"
+            log_level "debug"
+            levels "info" "debug"
+            thresholds {
+                debug 10
+                info 20
+            }
+            "#,
+        )
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            document,
+            Document {
+                log_level: Level::Debug,
+                levels: Vec::from([Level::Info, Level::Debug]),
+                thresholds: HashMap::from([(Level::Debug, 10), (Level::Info, 20)]),
+            }
+        );
+    }
+
+    #[test]
+    fn unit_variant_from_annotation() {
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Tagged {
+            #[serde(rename = "$kaydle::annotation")]
+            level: Level,
+            value: i32,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq, Eq)]
+        struct Document {
+            values: Vec<Tagged>,
+        }
+
+        let document: Document =
+            from_str("values (info)1 (debug)2").expect("failed to deserialize");
+
+        assert_eq!(
+            document.values,
+            [
+                Tagged {
+                    level: Level::Info,
+                    value: 1
+                },
+                Tagged {
+                    level: Level::Debug,
+                    value: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_unit_variant() {
+        #[derive(Deserialize, Debug)]
+        struct Document {
+            #[allow(dead_code)]
+            log_level: Level,
+        }
+
+        let err = from_str::<Document>(r#"log_level "trace""#).expect_err("unknown variant");
+        assert!(matches!(
+            err,
+            Error::UnknownVariant { ref variant, expected: ["debug", "info"] }
+            if variant == "trace"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "unknown variant \"trace\", expected one of: debug, info"
+        );
     }
 }
@@ -1,10 +1,11 @@
 use std::marker::PhantomData;
 
 use derive_new::new;
-use serde::de;
+use serde::{de, forward_to_deserialize_any};
 
 /// SeqAccess and MapAccess type that's always empty
 #[derive(new)]
+#[must_use]
 pub struct EmptyAccess<E> {
     error: PhantomData<E>,
 }
@@ -21,7 +22,6 @@ impl<'de, E: de::Error> de::SeqAccess<'de> for EmptyAccess<E> {
     }
 
     #[inline]
-    #[must_use]
     fn size_hint(&self) -> Option<usize> {
         Some(0)
     }
@@ -31,7 +31,6 @@ impl<'de, E: de::Error> de::MapAccess<'de> for EmptyAccess<E> {
     type Error = E;
 
     #[inline]
-
     fn next_key_seed<K>(&mut self, _seed: K) -> Result<Option<K::Value>, Self::Error>
     where
         K: de::DeserializeSeed<'de>,
@@ -61,8 +60,90 @@ impl<'de, E: de::Error> de::MapAccess<'de> for EmptyAccess<E> {
     }
 
     #[inline]
-    #[must_use]
     fn size_hint(&self) -> Option<usize> {
         Some(0)
     }
 }
+
+/// Deserializer for something that's absent, like the children of a node with
+/// no children block. Sequences and maps are empty, and options are `None`.
+#[derive(new)]
+pub struct EmptyDeserializer<E> {
+    error: PhantomData<E>,
+}
+
+impl<'de, E: de::Error> de::Deserializer<'de> for EmptyDeserializer<E> {
+    type Error = E;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct enum identifier
+        ignored_any
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(EmptyAccess::new())
+    }
+
+    #[inline]
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    #[inline]
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(EmptyAccess::new())
+    }
+
+    #[inline]
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+}
@@ -10,7 +10,7 @@ use serde::{
 };
 
 use crate::serde::{
-    de::{annotation::Deserializer as AnnotationDeserializer, Error},
+    de::{annotation::Deserializer as AnnotationDeserializer, bytes, Error, Options},
     magics,
 };
 
@@ -19,6 +19,29 @@ use super::raw;
 #[derive(Debug, Clone, new)]
 pub struct Deserializer<'a> {
     value: AnnotatedValue<'a>,
+    options: Options,
+}
+
+impl<'a> Deserializer<'a> {
+    /// Create a deserializer for a value, after checking it against its
+    /// annotation (if the options call for it)
+    pub fn checked(value: AnnotatedValue<'a>, options: Options) -> Result<Self, Error> {
+        options.check_value(&value)?;
+        Ok(Self::new(value, options))
+    }
+}
+
+/// Forward deserialize methods to the raw value, ignoring the annotation
+macro_rules! forward_to_raw {
+    ($($deserialize:ident)*) => {$(
+        #[inline]
+        fn $deserialize<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            raw::Deserializer::with_options(self.value.item, self.options).$deserialize(visitor)
+        }
+    )*};
 }
 
 // TODO: most of this implementation should forward directly to
@@ -32,13 +55,52 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        raw::Deserializer::new(self.value.item).deserialize_any(visitor)
+        raw::Deserializer::with_options(self.value.item, self.options).deserialize_any(visitor)
     }
 
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf unit unit_struct seq tuple tuple_struct map
-        identifier newtype_struct
+        char str string unit unit_struct seq tuple tuple_struct map identifier
+    }
+
+    forward_to_raw! {
+        deserialize_bool
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
+    #[inline]
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    /// Strings annotated with `(base64)` or `(hex)` are decoded into bytes.
+    /// Other values are forwarded, so that plain strings still work with
+    /// visitors that accept them.
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match bytes::decode(&self.value)? {
+            Some(bytes) => visitor.visit_byte_buf(bytes),
+            None => raw::Deserializer::with_options(self.value.item, self.options)
+                .deserialize_any(visitor),
+        }
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
     }
 
     #[inline]
@@ -46,7 +108,7 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        raw::Deserializer::new(self.value.item).deserialize_option(visitor)
+        raw::Deserializer::with_options(self.value.item, self.options).deserialize_option(visitor)
     }
 
     #[inline]
@@ -70,24 +132,34 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
         match *fields {
             [magics::ANNOTATION, field_name] | [field_name, magics::ANNOTATION] => visitor
                 .visit_map(serde_mobile::AccessAdapter::new(AnnotatedKeyAccess::new(
-                    field_name, self.value,
+                    field_name,
+                    self.value,
+                    self.options,
                 ))),
             _ if fields.contains(&magics::ANNOTATION) => Err(Error::InvalidAnnotatedValue),
-            _ => raw::Deserializer::new(self.value.item).deserialize_struct(name, fields, visitor),
+            _ => raw::Deserializer::with_options(self.value.item, self.options)
+                .deserialize_struct(name, fields, visitor),
         }
     }
 
+    /// Annotated values use the annotation as the enum discriminant. Values
+    /// without annotations are forwarded, so that strings can be used as
+    /// unit variants.
     #[inline]
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
+        name: &'static str,
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_enum(EnumAccess::new(self.value))
+        match self.value.annotation {
+            Some(..) => visitor.visit_enum(EnumAccess::new(self.value, self.options)),
+            None => raw::Deserializer::with_options(self.value.item, self.options)
+                .deserialize_enum(name, variants, visitor),
+        }
     }
 }
 
@@ -96,10 +168,12 @@ enum AnnotatedKeyAccess<'i> {
         value: KdlValue<'i>,
         field_name: &'static str,
         annotation: Option<KdlString<'i>>,
+        options: Options,
     },
     Field {
         value: KdlValue<'i>,
         field_name: &'static str,
+        options: Options,
     },
 }
 
@@ -108,18 +182,21 @@ enum AnnotatedValueAccess<'i> {
         value: KdlValue<'i>,
         field_name: &'static str,
         annotation: Option<KdlString<'i>>,
+        options: Options,
     },
     Field {
         value: KdlValue<'i>,
+        options: Options,
     },
 }
 
 impl<'i> AnnotatedKeyAccess<'i> {
-    pub fn new(field_name: &'static str, value: AnnotatedValue<'i>) -> Self {
+    pub fn new(field_name: &'static str, value: AnnotatedValue<'i>, options: Options) -> Self {
         Self::Annotation {
             value: value.item,
             annotation: value.annotation,
             field_name,
+            options,
         }
     }
 
@@ -129,17 +206,21 @@ impl<'i> AnnotatedKeyAccess<'i> {
                 value,
                 field_name,
                 annotation,
+                options,
             } => (
                 magics::ANNOTATION,
                 AnnotatedValueAccess::Annotation {
                     value,
                     field_name,
                     annotation,
+                    options,
                 },
             ),
-            AnnotatedKeyAccess::Field { value, field_name } => {
-                (field_name, AnnotatedValueAccess::Field { value })
-            }
+            AnnotatedKeyAccess::Field {
+                value,
+                field_name,
+                options,
+            } => (field_name, AnnotatedValueAccess::Field { value, options }),
         }
     }
 }
@@ -171,17 +252,22 @@ impl<'de> serde_mobile::MapValueAccess<'de> for AnnotatedValueAccess<'de> {
                 value,
                 field_name,
                 annotation,
+                options,
             } => seed
                 .deserialize(AnnotationDeserializer::new(annotation))
                 .map(|annotation| {
                     (
                         annotation,
-                        Some(AnnotatedKeyAccess::Field { value, field_name }),
+                        Some(AnnotatedKeyAccess::Field {
+                            value,
+                            field_name,
+                            options,
+                        }),
                     )
                 }),
 
-            AnnotatedValueAccess::Field { value } => seed
-                .deserialize(raw::Deserializer::new(value))
+            AnnotatedValueAccess::Field { value, options } => seed
+                .deserialize(raw::Deserializer::with_options(value, options))
                 .map(|value| (value, None)),
         }
     }
@@ -190,6 +276,7 @@ impl<'de> serde_mobile::MapValueAccess<'de> for AnnotatedValueAccess<'de> {
 #[derive(new)]
 struct EnumAccess<'i> {
     value: AnnotatedValue<'i>,
+    options: Options,
 }
 
 impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
@@ -203,13 +290,14 @@ impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
         let Annotated { annotation, item } = self.value;
 
         seed.deserialize(AnnotationDeserializer::new(annotation))
-            .map(|key| (key, VariantAccess::new(item)))
+            .map(|key| (key, VariantAccess::new(item, self.options)))
     }
 }
 
 #[derive(new)]
 struct VariantAccess<'i> {
     value: KdlValue<'i>,
+    options: Options,
 }
 
 impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
@@ -225,7 +313,7 @@ impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(raw::Deserializer::new(self.value))
+        seed.deserialize(raw::Deserializer::with_options(self.value, self.options))
     }
 
     #[inline]
@@ -248,3 +336,73 @@ impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
         Err(Error::NonNewtypeFromAnnotatedValue)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use crate::serde::de::{from_str, Error, Options};
+
+    #[test]
+    fn reserved_annotations() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Document {
+            small: u16,
+            address: String,
+        }
+
+        let input = r#"
+            small (u8)300
+            address (ipv4)"1.2.3.4"
+        "#;
+
+        let document: Document = from_str(input).expect("annotations aren't checked by default");
+        assert_eq!(document.small, 300);
+
+        let options = Options::strict();
+
+        let err = options
+            .from_str::<Document>(input)
+            .expect_err("300 isn't a u8");
+        assert!(matches!(
+            err,
+            Error::ReservedAnnotation(ref err) if err.annotation == "u8"
+        ));
+
+        let document: Document = options
+            .from_str(
+                r#"
+            small (u8)255
+            address (ipv4)"1.2.3.4"
+            "#,
+            )
+            .expect("valid annotations");
+        assert_eq!(document.address, "1.2.3.4");
+
+        let err = options
+            .from_str::<Document>(
+                r#"
+            small 1
+            address (ipv4)"nope"
+            "#,
+            )
+            .expect_err("not an address");
+        assert!(matches!(err, Error::ReservedAnnotation(_)));
+
+        // Properties, and arguments in sequences, are checked too
+        #[derive(Deserialize, Debug)]
+        struct Values {
+            #[allow(dead_code)]
+            values: Vec<i64>,
+            #[allow(dead_code)]
+            props: HashMap<String, i64>,
+        }
+
+        for input in ["values 1 (i8)200\nprops", "values\nprops a=(u8)-1"] {
+            let err = options.from_str::<Values>(input).expect_err(input);
+            assert!(matches!(err, Error::ReservedAnnotation(_)), "{input}");
+        }
+    }
+}
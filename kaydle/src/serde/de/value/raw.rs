@@ -1,15 +1,92 @@
 use std::marker::PhantomData;
 
 use derive_new::new;
-use kaydle_primitives::value::KdlValue;
+use kaydle_primitives::{
+    error::ContextualError,
+    number::{parse_number, KdlNumber},
+    value::KdlValue,
+};
+use nom::combinator::all_consuming;
 use serde::{de, forward_to_deserialize_any};
 
+use crate::serde::de::{string::Deserializer as StringDeserializer, Options};
+
 #[derive(Debug, Clone, new)]
 pub struct Deserializer<'a, E> {
     value: KdlValue<'a>,
+
+    /// If true, scalars are converted to the requested type; see
+    /// [`Options::lenient_scalars`]
+    #[new(default)]
+    lenient: bool,
+
     error: PhantomData<E>,
 }
 
+impl<'a, E> Deserializer<'a, E> {
+    pub fn with_options(value: KdlValue<'a>, options: Options) -> Self {
+        Self {
+            lenient: options.lenient_scalars,
+            ..Self::new(value)
+        }
+    }
+
+    /// In lenient mode, get the value as a number, if it's a number or a
+    /// string containing one.
+    fn lenient_number(&self) -> Option<KdlNumber> {
+        match self.value {
+            _ if !self.lenient => None,
+            KdlValue::Number(number) => Some(number),
+            KdlValue::String(ref s) => {
+                all_consuming(parse_number::<KdlNumber, ContextualError>)(s.as_str().trim())
+                    .ok()
+                    .map(|(_, number)| number)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Deserialize methods for integers. In lenient mode, integer-valued floats
+/// and numeric strings are converted.
+macro_rules! lenient_integers {
+    ($($deserialize:ident)*) => {$(
+        fn $deserialize<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            match self.lenient_number() {
+                // Floats in this range convert to i64 exactly
+                Some(KdlNumber::Float(value))
+                    if value.fract() == 0.0 && value.abs() < 9_223_372_036_854_775_808.0 =>
+                {
+                    visitor.visit_i64(value as i64)
+                }
+                Some(number) => number.visit_to(visitor),
+                None => self.deserialize_any(visitor),
+            }
+        }
+    )*};
+}
+
+/// Deserialize methods for floats. In lenient mode, integers and numeric
+/// strings are converted.
+macro_rules! lenient_floats {
+    ($($deserialize:ident)*) => {$(
+        fn $deserialize<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            match self.lenient_number() {
+                Some(KdlNumber::Signed(value)) => visitor.visit_f64(value as f64),
+                Some(KdlNumber::Unsigned(value)) => visitor.visit_f64(value as f64),
+                Some(KdlNumber::Float(value)) => visitor.visit_f64(value),
+                None => self.deserialize_any(visitor),
+            }
+        }
+    )*};
+}
+
 impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
     type Error = E;
 
@@ -22,9 +99,45 @@ impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
     }
 
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf unit unit_struct seq tuple tuple_struct map
-        identifier struct enum newtype_struct
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct
+        map identifier struct
+    }
+
+    lenient_integers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+    }
+
+    lenient_floats! { deserialize_f32 deserialize_f64 }
+
+    /// In lenient mode, common spellings of true and false are converted
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let KdlValue::String(ref s) = self.value {
+            if self.lenient {
+                match s.as_str().trim().to_ascii_lowercase().as_str() {
+                    "true" | "yes" | "on" | "1" => return visitor.visit_bool(true),
+                    "false" | "no" | "off" | "0" => return visitor.visit_bool(false),
+                    _ => {}
+                }
+            }
+        }
+
+        self.deserialize_any(visitor)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -37,6 +150,25 @@ impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
         }
     }
 
+    /// Strings can be deserialized as unit enum variants; other values are
+    /// forwarded to the visitor as usual, which will usually reject them.
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            KdlValue::String(value) => {
+                StringDeserializer::new(value).deserialize_enum(name, variants, visitor)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
     #[inline]
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
@@ -45,3 +177,72 @@ impl<'de, E: de::Error> de::Deserializer<'de> for Deserializer<'de, E> {
         visitor.visit_unit()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::serde::de::{from_str, Options};
+
+    #[test]
+    fn lenient_scalars() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Document {
+            port: u16,
+            enabled: bool,
+            disabled: bool,
+            ratio: f64,
+            count: i32,
+            hex: u8,
+            values: Vec<u64>,
+            name: String,
+        }
+
+        let input = r#"
+            port "8080"
+            enabled "Yes"
+            disabled "off"
+            ratio "0.5"
+            count 1e3
+            hex "0xff"
+            values "1" 2 3.0
+            name "kaydle"
+        "#;
+
+        let options = Options::permissive();
+
+        let document: Document = options.from_str(input).expect("lenient");
+        assert_eq!(
+            document,
+            Document {
+                port: 8080,
+                enabled: true,
+                disabled: false,
+                ratio: 0.5,
+                count: 1000,
+                hex: 255,
+                values: vec![1, 2, 3],
+                name: "kaydle".to_owned(),
+            }
+        );
+
+        from_str::<Document>(input).expect_err("strict by default");
+
+        #[derive(Deserialize, Debug)]
+        struct Scalar<T> {
+            #[allow(dead_code)]
+            value: T,
+        }
+
+        for input in [r#"value "maybe""#, "value 1.5", r#"value "x1""#] {
+            options.from_str::<Scalar<u8>>(input).expect_err(input);
+        }
+
+        options
+            .from_str::<Scalar<u8>>("value 256")
+            .expect_err("out of range");
+        options
+            .from_str::<Scalar<bool>>(r#"value "maybe""#)
+            .expect_err("maybe");
+    }
+}
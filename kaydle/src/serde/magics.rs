@@ -1,17 +1,32 @@
 /// Kaydle magic for extracting an annotation
 pub const ANNOTATION: &str = "$kaydle::annotation";
 
+/// Kaydle magic prefix for collecting all the nodes with a given name in a
+/// node list into a sequence. For instance, a `$kaydle::repeated::plugin` field
+/// will contain every `plugin` node.
+pub const REPEATED: &str = "$kaydle::repeated::";
+
+/// Kaydle magic prefix for collecting all the nodes with a given name in a
+/// node list into a map, keyed by the first argument of each node. For
+/// instance, a `$kaydle::keyed::server` field will contain every `server`
+/// node, keyed by the server's name.
+pub const KEYED: &str = "$kaydle::keyed::";
+
+/// Kaydle magic for collecting everything that wasn't deserialized into some
+/// other field: unknown properties of a node, or unknown nodes in a node list.
+pub const REST: &str = "$kaydle::rest";
+
 // /// Kaydle magic for extracting the name of a node
 // pub const NODE_NAME: &str = "$kaydle::name";
 
-// /// Kaydle magic for extracting the properties of a node
-// pub const PROPERTIES: &str = "$kaydle::properties";
+/// Kaydle magic for extracting the properties of a node
+pub const PROPERTIES: &str = "$kaydle::properties";
 
-// /// Kaydle magic for extracting the arguments of a node
-// pub const ARGUMENTS: &str = "$kaydle::arguments";
+/// Kaydle magic for extracting the arguments of a node
+pub const ARGUMENTS: &str = "$kaydle::arguments";
 
-// /// Kaydle magic for extracting the children of a node
-// pub const CHILDREN: &str = "$kaydle::children";
+/// Kaydle magic for extracting the children of a node
+pub const CHILDREN: &str = "$kaydle::children";
 
 // /// Kaydle magic for forwarding the entire node to some inner struct field
 // pub const TRANSPARENT: &str = "$kaydle::transparent";
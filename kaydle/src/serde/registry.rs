@@ -0,0 +1,442 @@
+/*!
+A runtime registry of node types, for deserializing lists of nodes into
+trait objects.
+
+Applications with plugins often can't know every node type at compile time.
+A [`NodeRegistry`] maps node names to the concrete types they should be
+deserialized into, and deserializes each node into a `Box<T>` (usually a
+`Box<dyn Trait>`), in the spirit of `typetag` but keyed by KDL node names:
+
+```
+use kaydle::serde::NodeRegistry;
+use serde::Deserialize;
+
+trait Sink {
+    fn describe(&self) -> String;
+}
+
+#[derive(Deserialize)]
+struct S3Sink {
+    bucket: String,
+}
+
+impl Sink for S3Sink {
+    fn describe(&self) -> String {
+        format!("s3://{}", self.bucket)
+    }
+}
+
+#[derive(Deserialize)]
+struct FileSink(String);
+
+impl Sink for FileSink {
+    fn describe(&self) -> String {
+        format!("file://{}", self.0)
+    }
+}
+
+let mut registry = NodeRegistry::<dyn Sink>::new();
+registry
+    .register_with("s3-sink", |sink: S3Sink| Box::new(sink))
+    .register_with("file-sink", |sink: FileSink| Box::new(sink));
+
+let sinks = registry.from_str(r#"
+    s3-sink bucket="logs"
+    file-sink "/var/log/app.log"
+"#).unwrap();
+
+let descriptions: Vec<String> = sinks.iter().map(|sink| sink.describe()).collect();
+assert_eq!(descriptions, ["s3://logs", "file:///var/log/app.log"]);
+```
+
+Registries of trait objects have to use [`NodeRegistry::register_with`],
+with a conversion that boxes each value. [`NodeRegistry::register`] takes
+the conversion from a `U: Into<Box<T>>` bound instead, which never holds
+when `T` is a `dyn Trait` (there's no `From<U> for Box<dyn Trait>`), so it
+only works for registries of a sized type, like `NodeRegistry<u16>`.
+
+Each node is deserialized like a variant of an un-annotated enum: the node
+name picks the type, and the rest of the node (its arguments, properties,
+and children) is deserialized into that type. Nodes with a name that hasn't
+been registered are an error.
+
+[`NodeRegistry::node`] deserializes a single node, and `&NodeRegistry`
+is itself a [`DeserializeSeed`] for a list of nodes, so registries can be
+used with [`Options::from_str_seed`][crate::serde::de::Options::from_str_seed].
+Other formats can use them too, as long as they represent each node like an
+externally tagged enum variant.
+*/
+
+mod capture;
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed};
+
+use super::de::{Error, Options};
+
+/// A type-erased function that deserializes a registered type and boxes it
+type DeserializeFn<T> =
+    dyn Fn(&mut dyn erased_serde::Deserializer<'_>) -> erased_serde::Result<Box<T>> + Send + Sync;
+
+/// A registry mapping node names to the types they're deserialized into. See
+/// the [module docs][self] for details.
+pub struct NodeRegistry<T: ?Sized + 'static> {
+    types: BTreeMap<String, Box<DeserializeFn<T>>>,
+}
+
+impl<T: ?Sized + 'static> NodeRegistry<T> {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            types: BTreeMap::new(),
+        }
+    }
+
+    /// Register the type `U` under the node name `name`, so that nodes with
+    /// that name are deserialized as a `U` and converted into a `Box<T>`.
+    /// Registering a name again replaces the earlier registration.
+    ///
+    /// This requires `U: Into<Box<T>>`, which holds when `T` is `U`; for
+    /// trait objects, use [`register_with`][Self::register_with].
+    pub fn register<U>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        U: DeserializeOwned + Into<Box<T>> + 'static,
+    {
+        self.register_with(name, U::into)
+    }
+
+    /// Register the type `U` under the node name `name`, so that nodes with
+    /// that name are deserialized as a `U` and boxed with `convert`. This is
+    /// usually just `|value: U| Box::new(value)`, which coerces the box to a
+    /// `Box<dyn Trait>`. Registering a name again replaces the earlier
+    /// registration.
+    pub fn register_with<U>(
+        &mut self,
+        name: impl Into<String>,
+        convert: impl Fn(U) -> Box<T> + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        U: DeserializeOwned + 'static,
+    {
+        self.types.insert(
+            name.into(),
+            Box::new(move |deserializer| erased_serde::deserialize(deserializer).map(&convert)),
+        );
+        self
+    }
+
+    /// Check if a node name has been registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+    }
+
+    /// The registered node names, in sorted order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+
+    /// A [`DeserializeSeed`] for a single named node, which uses the node
+    /// name to pick the type
+    pub fn node(&self) -> NodeSeed<'_, T> {
+        NodeSeed { registry: self }
+    }
+
+    /// Deserialize every node in a string containing a KDL document, using
+    /// the default [`Options`]
+    pub fn from_str(&self, input: &str) -> Result<Vec<Box<T>>, Error> {
+        Options::new().from_str_seed(self, input)
+    }
+}
+
+impl<T: ?Sized + 'static> Default for NodeRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized + 'static> fmt::Debug for NodeRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeRegistry")
+            .field("names", &self.types.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// A list of nodes is deserialized into a list of boxed values
+impl<'de, T: ?Sized + 'static> DeserializeSeed<'de> for &NodeRegistry<T> {
+    type Value = Vec<Box<T>>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: ?Sized + 'static> de::Visitor<'de> for &NodeRegistry<T> {
+    type Value = Vec<Box<T>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a list of registered nodes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(value) = seq.next_element_seed(self.node())? {
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+}
+
+/// A [`DeserializeSeed`] for a single node from a [`NodeRegistry`], created
+/// with [`NodeRegistry::node`]
+pub struct NodeSeed<'r, T: ?Sized + 'static> {
+    registry: &'r NodeRegistry<T>,
+}
+
+impl<T: ?Sized + 'static> fmt::Debug for NodeSeed<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeSeed")
+            .field("registry", self.registry)
+            .finish()
+    }
+}
+
+impl<'de, T: ?Sized + 'static> DeserializeSeed<'de> for NodeSeed<'_, T> {
+    type Value = Box<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        // Named nodes are deserialized as enums by using the node name as
+        // the variant, so the registered names act as the variants.
+        deserializer.deserialize_enum("NodeRegistry", &[], self)
+    }
+}
+
+impl<'de, T: ?Sized + 'static> de::Visitor<'de> for NodeSeed<'_, T> {
+    type Value = Box<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a node with a registered name")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        let (name, variant): (String, _) = data.variant()?;
+
+        let deserialize = self.registry.types.get(&name).ok_or_else(|| {
+            let names: Vec<&str> = self.registry.names().collect();
+            de::Error::custom(format_args!(
+                "no node type is registered as `{name}`; registered names are: {}",
+                names.join(", ")
+            ))
+        })?;
+
+        de::VariantAccess::newtype_variant_seed(variant, ErasedSeed { deserialize })
+    }
+}
+
+/// Adapts a registered [`DeserializeFn`] to deserialize from any
+/// deserializer, by erasing its type. The deserializer's own errors are
+/// returned as they are, rather than as strings.
+struct ErasedSeed<'r, T: ?Sized + 'static> {
+    deserialize: &'r DeserializeFn<T>,
+}
+
+impl<'de, T: ?Sized + 'static> DeserializeSeed<'de> for ErasedSeed<'_, T> {
+    type Value = Box<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        capture::deserialize(deserializer, self.deserialize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{de, Deserialize};
+
+    use super::NodeRegistry;
+    use crate::serde::de::{Error, Options};
+
+    trait Sink: std::fmt::Debug {
+        fn describe(&self) -> String;
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct S3Sink {
+        bucket: String,
+        #[serde(default)]
+        region: String,
+    }
+
+    impl Sink for S3Sink {
+        fn describe(&self) -> String {
+            match self.region.as_str() {
+                "" => format!("s3://{}", self.bucket),
+                region => format!("s3://{}@{region}", self.bucket),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct FileSink(String);
+
+    impl Sink for FileSink {
+        fn describe(&self) -> String {
+            format!("file://{}", self.0)
+        }
+    }
+
+    fn sinks() -> NodeRegistry<dyn Sink> {
+        let mut registry = NodeRegistry::<dyn Sink>::new();
+        registry
+            .register_with("s3-sink", |sink: S3Sink| Box::new(sink))
+            .register_with("file-sink", |sink: FileSink| Box::new(sink));
+        registry
+    }
+
+    #[test]
+    fn registered_nodes() {
+        let registry = sinks();
+
+        assert!(registry.contains("s3-sink"));
+        assert!(!registry.contains("S3Sink"));
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["file-sink", "s3-sink"]
+        );
+
+        let input = r#"
+            s3-sink {
+                bucket "logs"
+                region "eu-west-1"
+            }
+            file-sink "/var/log/app.log"
+            s3-sink bucket="backups"
+        "#;
+
+        let sinks = registry.from_str(input).expect("registered nodes");
+        let descriptions: Vec<String> = sinks.iter().map(|sink| sink.describe()).collect();
+        assert_eq!(
+            descriptions,
+            [
+                "s3://logs@eu-west-1",
+                "file:///var/log/app.log",
+                "s3://backups"
+            ]
+        );
+
+        let sinks = Options::new()
+            .from_str_seed(&registry, r#"file-sink "/tmp/out""#)
+            .expect("registry with options");
+        assert_eq!(sinks.len(), 1);
+
+        let err = registry
+            .from_str(r#"kafka-sink topic="logs""#)
+            .expect_err("unregistered node");
+        assert_eq!(
+            err.to_string(),
+            "error from Deserialize type: no node type is registered as `kafka-sink`; \
+            registered names are: file-sink, s3-sink"
+        );
+
+        registry
+            .from_str(r#"s3-sink region="eu-west-1""#)
+            .expect_err("missing field");
+
+        // Sized types can be registered by type alone
+        let mut ports = NodeRegistry::<u16>::default();
+        ports.register::<u16>("http").register::<u16>("https");
+
+        let ports = ports
+            .from_str("http 80; https 443")
+            .expect("sized registry");
+        assert_eq!(ports, [Box::new(80), Box::new(443)]);
+    }
+
+    /// Errors from inside a registered type are the same as they would be
+    /// without the registry
+    #[test]
+    fn errors_keep_their_variants() {
+        let registry = sinks();
+
+        #[derive(Deserialize, Debug)]
+        enum Unregistered {
+            #[serde(rename = "s3-sink")]
+            S3(#[allow(dead_code)] S3Sink),
+        }
+
+        let input = "s3-sink bucket=\"ok\"\ns3-sink bucket=\"abc\\q\"";
+        let expected = match crate::serde::from_str::<Vec<Unregistered>>(input) {
+            Err(Error::ParseError(err)) => err,
+            result => panic!("expected a parse error, got {result:?}"),
+        };
+
+        match registry.from_str(input) {
+            Err(Error::ParseError(err)) => {
+                assert_eq!(err, expected);
+                assert_eq!(err.error.offset(), 40);
+            }
+            result => panic!("expected a parse error, got {result:?}"),
+        }
+
+        let err = registry
+            .from_str("s3-sink bucket=1")
+            .expect_err("wrong type");
+        assert_eq!(
+            err.to_string(),
+            "error from Deserialize type: invalid type: integer `1`, expected a string"
+        );
+
+        let err = registry
+            .from_str(r#"file-sink "a" "b""#)
+            .expect_err("too many arguments");
+        assert!(matches!(err, Error::UnfinishedNode { .. }));
+    }
+
+    /// Errors that a registered type replaces with its own aren't swapped
+    /// back for the original
+    #[test]
+    fn replaced_errors() {
+        #[derive(Debug)]
+        struct NamedSink(String);
+
+        impl<'de> Deserialize<'de> for NamedSink {
+            fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)
+                    .map(NamedSink)
+                    .map_err(|_| de::Error::custom("sink names must be strings"))
+            }
+        }
+
+        impl Sink for NamedSink {
+            fn describe(&self) -> String {
+                format!("named://{}", self.0)
+            }
+        }
+
+        let mut registry = sinks();
+        registry.register_with("named-sink", |sink: NamedSink| Box::new(sink));
+
+        let err = registry.from_str("named-sink 1").expect_err("not a string");
+        assert_eq!(
+            err.to_string(),
+            "error from Deserialize type: sink names must be strings"
+        );
+    }
+}
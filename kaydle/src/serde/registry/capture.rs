@@ -0,0 +1,392 @@
+/*!
+Keeps a deserializer's own errors intact while it's being driven through
+`erased_serde`.
+
+`erased_serde` turns every error that crosses it into a string, so the
+structured variants of [`Error`][crate::serde::de::Error] (and the offsets
+of parse errors) would be lost by the time they came back out. Instead, the
+deserializer and everything it hands to the erased code (visitors, seeds,
+and accesses) are wrapped, and each error is stashed just before it's
+erased. When an error comes back out at the same level, the stashed original
+is returned in place of the stringified copy, unless the erased code replaced
+the copy with an error of its own.
+*/
+
+use std::{cell::Cell, fmt};
+
+use serde::de;
+
+/// Deserialize with a function that takes an erased deserializer, returning
+/// the deserializer's original errors.
+pub fn deserialize<'de, D, T>(
+    deserializer: D,
+    deserialize: impl FnOnce(&mut dyn erased_serde::Deserializer<'de>) -> erased_serde::Result<T>,
+) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let slot = Slot::new();
+    let mut erased = <dyn erased_serde::Deserializer>::erase(Deserializer {
+        inner: deserializer,
+        slot: &slot,
+    });
+
+    slot.recover(deserialize(&mut erased).map_err(de::Error::custom))
+}
+
+/// The most recent error that was erased at one level of the deserialize,
+/// along with the text of its copy as it comes back out of the erased code
+struct Slot<E> {
+    error: Cell<Option<(E, String)>>,
+}
+
+impl<E: de::Error> Slot<E> {
+    fn new() -> Self {
+        Self {
+            error: Cell::new(None),
+        }
+    }
+
+    /// Keep an error that's about to be erased, and return a copy of it to
+    /// erase instead
+    fn stash(&self, error: E) -> E {
+        let copy = E::custom(&error);
+
+        // Errors come back out of the erased code through `custom`
+        let returned = E::custom(&copy).to_string();
+        self.error.set(Some((error, returned)));
+        copy
+    }
+
+    /// Forget the stashed error, if the erased code recovered from it and
+    /// carried on
+    fn clear(&self) {
+        self.error.set(None);
+    }
+
+    /// Swap an error that came back out of the erased code for the original,
+    /// if it's still the stashed error's copy. Errors that the erased code
+    /// replaced with its own are returned as they are.
+    fn recover<T>(&self, result: Result<T, E>) -> Result<T, E> {
+        let stashed = self.error.take();
+        result.map_err(|error| match stashed {
+            Some((original, returned)) if error.to_string() == returned => original,
+            _ => error,
+        })
+    }
+}
+
+struct Deserializer<'s, D, E> {
+    inner: D,
+    slot: &'s Slot<E>,
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $type:ty),*);)*) => {$(
+        fn $method<V>(self, $($arg: $type,)* visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            let slot = self.slot;
+
+            self.inner
+                .$method($($arg,)* Visitor(visitor))
+                .map_err(|error| slot.stash(error))
+        }
+    )*};
+}
+
+impl<'de, D: de::Deserializer<'de>> de::Deserializer<'de> for Deserializer<'_, D, D::Error> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct Visitor<V>(V);
+
+macro_rules! forward_visit {
+    ($($method:ident($type:ty);)*) => {$(
+        fn $method<E>(self, value: $type) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            self.0.$method(value)
+        }
+    )*};
+}
+
+impl<'de, V: de::Visitor<'de>> de::Visitor<'de> for Visitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_string(String);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+        visit_byte_buf(Vec<u8>);
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_none()
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let slot = Slot::new();
+        slot.recover(self.0.visit_some(Deserializer {
+            inner: deserializer,
+            slot: &slot,
+        }))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let slot = Slot::new();
+        slot.recover(self.0.visit_newtype_struct(Deserializer {
+            inner: deserializer,
+            slot: &slot,
+        }))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let slot = Slot::new();
+        slot.recover(self.0.visit_seq(Access {
+            inner: seq,
+            slot: &slot,
+        }))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let slot = Slot::new();
+        slot.recover(self.0.visit_map(Access {
+            inner: map,
+            slot: &slot,
+        }))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        let slot = Slot::new();
+        slot.recover(self.0.visit_enum(Access {
+            inner: data,
+            slot: &slot,
+        }))
+    }
+}
+
+struct Seed<T>(T);
+
+impl<'de, T: de::DeserializeSeed<'de>> de::DeserializeSeed<'de> for Seed<T> {
+    type Value = T::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let slot = Slot::new();
+        slot.recover(self.0.deserialize(Deserializer {
+            inner: deserializer,
+            slot: &slot,
+        }))
+    }
+}
+
+/// Wrapper for each of the access types
+struct Access<'s, A, E> {
+    inner: A,
+    slot: &'s Slot<E>,
+}
+
+impl<'de, A: de::SeqAccess<'de>> de::SeqAccess<'de> for Access<'_, A, A::Error> {
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.slot.clear();
+        self.inner
+            .next_element_seed(Seed(seed))
+            .map_err(|error| self.slot.stash(error))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for Access<'_, A, A::Error> {
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        self.slot.clear();
+        self.inner
+            .next_key_seed(Seed(seed))
+            .map_err(|error| self.slot.stash(error))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        self.slot.clear();
+        self.inner
+            .next_value_seed(Seed(seed))
+            .map_err(|error| self.slot.stash(error))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'s, 'de, A: de::EnumAccess<'de>> de::EnumAccess<'de> for Access<'s, A, A::Error> {
+    type Error = A::Error;
+    type Variant = Access<'s, A::Variant, A::Error>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let slot = self.slot;
+        slot.clear();
+
+        match self.inner.variant_seed(Seed(seed)) {
+            Ok((value, variant)) => Ok((
+                value,
+                Access {
+                    inner: variant,
+                    slot,
+                },
+            )),
+            Err(error) => Err(slot.stash(error)),
+        }
+    }
+}
+
+impl<'de, A: de::VariantAccess<'de>> de::VariantAccess<'de> for Access<'_, A, A::Error> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        let slot = self.slot;
+        slot.clear();
+        self.inner.unit_variant().map_err(|error| slot.stash(error))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let slot = self.slot;
+        slot.clear();
+        self.inner
+            .newtype_variant_seed(Seed(seed))
+            .map_err(|error| slot.stash(error))
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let slot = self.slot;
+        slot.clear();
+        self.inner
+            .tuple_variant(len, Visitor(visitor))
+            .map_err(|error| slot.stash(error))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let slot = self.slot;
+        slot.clear();
+        self.inner
+            .struct_variant(fields, Visitor(visitor))
+            .map_err(|error| slot.stash(error))
+    }
+}